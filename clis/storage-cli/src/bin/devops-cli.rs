@@ -1,21 +1,31 @@
 use anyhow::{Ok, Result};
 use clap::Parser;
 use log::debug;
-use storage_cli::{cmd::DevopsCmd, conf::DevopsConf, operators::get_operator};
+use storage_cli::{
+  cmd::{DevopsCmd, FileOperation, set_output_format, set_progress_enabled},
+  conf::DevopsConf,
+};
 
-#[tokio::main]
-async fn main() -> Result<()> {
-  logforth::stdout().apply();
+fn main() -> Result<()> {
+  // 日志输出到标准错误，避免与 `get -`、`cat` 等输出到标准输出的数据混在一起
+  logforth::stderr().apply();
 
   let cmd = DevopsCmd::parse();
   debug!("args is {:?}", cmd);
+  set_progress_enabled(!cmd.no_progress);
+  set_output_format(cmd.output);
 
-  let conf = DevopsConf::from_devops_cmd(&cmd)?;
-
-  if let Some(file_op) = cmd.file_op {
-    let op = get_operator(&conf).await?;
-    file_op.execute(&op).await?;
+  if let Some(FileOperation::Config { op }) = cmd.file_op.as_ref() {
+    // config 命令需在配置不完整时也能执行，不预先加载配置
+    return op.execute(&cmd);
   }
 
-  Ok(())
+  let conf = DevopsConf::from_devops_cmd(&cmd)?;
+
+  tokio::runtime::Builder::new_multi_thread().enable_all().build()?.block_on(async {
+    if let Some(file_op) = cmd.file_op {
+      file_op.execute(&conf).await?;
+    }
+    Ok(())
+  })
 }
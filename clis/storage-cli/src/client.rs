@@ -0,0 +1,307 @@
+use std::{
+  path::{Path, PathBuf},
+  sync::Arc,
+};
+
+use futures::{StreamExt, TryStreamExt, stream::BoxStream};
+use opendal::{Metakey, Operator};
+use serde::Serialize;
+
+use crate::{
+  cmd::{
+    ByteRange, ExistingPolicy, HttpClient, MultipartUploader, ObjectMeta, Progress, StorageSource, UploadOptions,
+    default_journal_dir, get_object_key_to_dst, get_prefix_to_dir, put_dir_to_prefix, put_src_to_object_key,
+    resolve_range,
+  },
+  conf::{DevopsConf, StorageConf, TransferConf},
+  credentials::expand_home,
+  error::StorageError,
+  operators::{build_operator, get_operator, with_transfer_layers},
+};
+
+/// 单个文件的传输结果，校验和为本次传输内容的校验和，md5 与 sha256 为十六进制小写
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TransferResult {
+  pub object_key: String,
+  pub bytes: u64,
+  pub md5: String,
+  pub sha256: String,
+  pub crc64: u64,
+}
+
+/// 目录传输的结果，单个文件的失败不中断其余文件的传输
+#[derive(Debug, Default)]
+pub struct DirTransferResult {
+  /// 传输成功的文件，按完成的顺序排列
+  pub transferred: Vec<TransferResult>,
+  /// 本地文件已存在且策略为跳过的对象 key
+  pub skipped: Vec<String>,
+  /// 传输失败的对象 key 及错误
+  pub failed: Vec<(String, StorageError)>,
+}
+
+/// 对象存储客户端，提供上传、下载、查询、列举与删除，返回结构化的结果，不输出到标准输出。复制、移动与预览
+/// 等命令直接使用 [`operator`](Self::operator)
+#[derive(Debug, Clone)]
+pub struct StorageClient {
+  op: Operator,
+  /// 绕过 opendal 的请求（如校验时获取对象的响应头）使用的 HTTP 客户端，由 Operator 创建且构建失败时为 `None`
+  http: Option<HttpClient>,
+  /// 断点续传上传使用的分段上传接口，存储服务不支持或由 Operator 创建时为 `None`
+  multipart: Option<Arc<MultipartUploader>>,
+  /// 断点续传日志的目录，未设置时使用默认目录
+  journal_dir: Option<PathBuf>,
+  progress: bool,
+}
+
+impl StorageClient {
+  /// 使用配置中的 `[storage]` 与 `[transfer]` 创建
+  pub async fn new(conf: &DevopsConf) -> Result<Self, StorageError> {
+    let client = async {
+      let op = get_operator(conf).await?;
+      let http = HttpClient::new(conf.transfer())?;
+      let multipart = match conf.storage() {
+        Some(sc) => MultipartUploader::new(conf.service_of(sc), sc, &http, conf.transfer())?.map(Arc::new),
+        None => None,
+      };
+      let journal_dir = conf.transfer().journal_dir.as_deref().map(expand_home);
+      Ok(Self { op, http: Some(http), multipart, journal_dir, progress: false })
+    };
+    client.await.map_err(StorageError::Config)
+  }
+
+  /// 不使用配置文件，通过代码设置存储配置
+  pub fn builder(service: StorageSource) -> StorageClientBuilder {
+    StorageClientBuilder { service, storage: StorageConf::default(), transfer: TransferConf::default() }
+  }
+
+  /// 使用已构建的 Operator 创建，绕过 opendal 的请求使用默认的 `[transfer]` 配置
+  pub fn from_operator(op: Operator) -> Self {
+    let http = HttpClient::new(&TransferConf::default()).ok();
+    Self { op, http, multipart: None, journal_dir: None, progress: false }
+  }
+
+  /// 是否在标准错误显示传输进度条，默认不显示。标准错误不是终端时始终不显示
+  pub fn with_progress(mut self, enabled: bool) -> Self {
+    self.progress = enabled;
+    self
+  }
+
+  /// 断点续传日志的目录，默认为 `[transfer] journal_dir` 或 `$XDG_STATE_HOME/devops-cli/journal`
+  pub fn with_journal_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+    self.journal_dir = Some(dir.into());
+    self
+  }
+
+  pub fn operator(&self) -> &Operator {
+    &self.op
+  }
+
+  pub(crate) fn http(&self) -> Option<&HttpClient> {
+    self.http.as_ref()
+  }
+
+  pub(crate) fn journal_dir(&self) -> PathBuf {
+    self.journal_dir.clone().unwrap_or_else(default_journal_dir)
+  }
+
+  pub(crate) fn multipart(&self) -> Option<&MultipartUploader> {
+    self.multipart.as_deref()
+  }
+
+  /// 上传本地文件，完成后校验对象的完整性
+  pub async fn upload_file(&self, src: impl AsRef<Path>, object_key: &str) -> Result<TransferResult, StorageError> {
+    self.upload_file_with(src, object_key, &UploadOptions::default()).await
+  }
+
+  /// 按 options 上传本地文件，内容大于 `chunk_size` 时分段并发上传
+  pub async fn upload_file_with(
+    &self,
+    src: impl AsRef<Path>,
+    object_key: &str,
+    options: &UploadOptions,
+  ) -> Result<TransferResult, StorageError> {
+    let src = src.as_ref();
+    let result = async {
+      let progress = self.progress(object_key, Some(tokio::fs::metadata(src).await?.len()));
+      // `-` 在命令行中表示标准输入，作为文件名时需加上 `./` 前缀
+      let path = match src.to_string_lossy() {
+        p if p == "-" => "./-".to_string(),
+        p => p.into_owned(),
+      };
+      let result = put_src_to_object_key(self, &path, object_key, options, &progress).await;
+      progress.finish();
+      Ok(result?)
+    };
+    result.await.map_err(|e: StorageError| e.with_path(object_key, src))
+  }
+
+  /// 下载对象到本地文件，覆盖已存在的文件，完成后校验完整性
+  pub async fn download_file(&self, object_key: &str, dst: impl AsRef<Path>) -> Result<TransferResult, StorageError> {
+    let dst = dst.as_ref();
+    self
+      .download_file_with(object_key, dst, ExistingPolicy::Overwrite, None)
+      .await?
+      .ok_or_else(|| StorageError::AlreadyExists(dst.display().to_string()))
+  }
+
+  /// 下载对象到本地文件，本地文件已存在且策略为跳过时返回 `None`。指定 range 时仅下载该字节范围，不校验完整性
+  pub async fn download_file_with(
+    &self,
+    object_key: &str,
+    dst: impl AsRef<Path>,
+    policy: ExistingPolicy,
+    range: Option<ByteRange>,
+  ) -> Result<Option<TransferResult>, StorageError> {
+    let dst = dst.as_ref();
+    let result = async {
+      let resolved = resolve_range(&self.op, object_key, range).await?;
+      let progress = self.progress(object_key, Some(resolved.end - resolved.start));
+      let range = range.map(|_| resolved);
+      let result = get_object_key_to_dst(self, object_key, &dst.to_string_lossy(), policy, range, &progress).await;
+      progress.finish();
+      Ok(result?)
+    };
+    result.await.map_err(|e: StorageError| e.with_path(object_key, dst))
+  }
+
+  /// 递归上传本地目录到 prefix 下，jobs 为并发上传的文件数。不跟随符号链接，遇到时输出警告并跳过
+  pub async fn upload_dir(
+    &self,
+    src: impl AsRef<Path>,
+    prefix: &str,
+    jobs: usize,
+    options: &UploadOptions,
+  ) -> Result<DirTransferResult, StorageError> {
+    let src = src.as_ref();
+    let result = put_dir_to_prefix(self, src, prefix, jobs, options).await;
+    result.map_err(|e| StorageError::from(e).with_path(prefix, src))
+  }
+
+  /// 递归下载 prefix 下的所有对象到本地目录，jobs 为并发下载的文件数。key 无法映射到目录内的路径（如包含 `..`）
+  /// 的对象记为失败
+  pub async fn download_dir(
+    &self,
+    prefix: &str,
+    dst: impl AsRef<Path>,
+    jobs: usize,
+    policy: ExistingPolicy,
+  ) -> Result<DirTransferResult, StorageError> {
+    let dst = dst.as_ref();
+    let result = get_prefix_to_dir(self, prefix, dst, jobs, policy).await;
+    result.map_err(|e| StorageError::from(e).with_path(prefix, dst))
+  }
+
+  /// 查询对象元信息
+  pub async fn stat(&self, object_key: &str) -> Result<ObjectMeta, StorageError> {
+    match self.op.stat(object_key).await {
+      Ok(md) => Ok(ObjectMeta::new(object_key, &md)),
+      Err(e) => Err(StorageError::from(e).with_path(object_key, Path::new(object_key))),
+    }
+  }
+
+  /// 列出前缀下的对象，结果包含 mode、content_length、last_modified 与 etag。limit 同时作为每页请求的数量
+  pub async fn list(
+    &self,
+    prefix: &str,
+    recursive: bool,
+    limit: Option<usize>,
+  ) -> Result<Vec<ObjectMeta>, StorageError> {
+    self.list_stream(prefix, recursive, limit).await?.try_collect().await
+  }
+
+  /// 与 [`list`](Self::list) 相同，但按分页逐个返回对象，不在内存中保存全部结果，适合对象很多的前缀
+  pub async fn list_stream(
+    &self,
+    prefix: &str,
+    recursive: bool,
+    limit: Option<usize>,
+  ) -> Result<BoxStream<'static, Result<ObjectMeta, StorageError>>, StorageError> {
+    let mut fl = self
+      .op
+      .lister_with(prefix)
+      .recursive(recursive)
+      .metakey(Metakey::Mode | Metakey::ContentLength | Metakey::LastModified | Metakey::Etag);
+    if let Some(limit) = limit {
+      fl = fl.limit(limit);
+    }
+    let lister = fl.await?;
+    let entries = lister.map_ok(|entry| ObjectMeta::new(entry.path(), entry.metadata())).map_err(StorageError::from);
+    Ok(entries.take(limit.unwrap_or(usize::MAX)).boxed())
+  }
+
+  /// 删除对象，对象不存在时不返回错误
+  pub async fn delete(&self, object_key: &str) -> Result<(), StorageError> {
+    Ok(self.op.delete(object_key).await?)
+  }
+
+  /// 批量删除对象，存储支持时使用批量删除接口
+  pub async fn delete_all(&self, object_keys: Vec<String>) -> Result<(), StorageError> {
+    Ok(self.op.remove(object_keys).await?)
+  }
+
+  fn progress(&self, name: &str, total: Option<u64>) -> Progress {
+    if self.progress { Progress::new(name, total) } else { Progress::hidden() }
+  }
+
+  /// 多个文件的汇总进度
+  pub(crate) fn files_progress(&self, total_files: usize, total_bytes: u64) -> Progress {
+    if self.progress { Progress::files(total_files, total_bytes) } else { Progress::hidden() }
+  }
+}
+
+/// [`StorageClient`] 的构建器，未设置的配置项使用默认值
+#[derive(Debug)]
+pub struct StorageClientBuilder {
+  service: StorageSource,
+  storage: StorageConf,
+  transfer: TransferConf,
+}
+
+impl StorageClientBuilder {
+  pub fn endpoint(mut self, endpoint: &str) -> Self {
+    self.storage.endpoint = endpoint.to_string();
+    self
+  }
+
+  pub fn bucket(mut self, bucket: &str) -> Self {
+    self.storage.bucket = bucket.to_string();
+    self
+  }
+
+  pub fn credentials(mut self, ak: &str, sk: &str) -> Self {
+    self.storage.ak = ak.to_string();
+    self.storage.sk = sk.to_string();
+    self
+  }
+
+  /// STS 临时凭证的 token。opendal 的 OSS、OBS、COS 仅从进程环境变量读取 token，本库不会修改环境变量，这些存储
+  /// 需由调用方在启动前导出相同的 token（`ALIBABA_CLOUD_SECURITY_TOKEN`、`HUAWEI_CLOUD_SECURITY_TOKEN`、
+  /// `TENCENTCLOUD_SECURITY_TOKEN`），不一致时 [`build`](Self::build) 返回错误
+  pub fn security_token(mut self, token: &str) -> Self {
+    self.storage.security_token = Some(token.to_string());
+    self
+  }
+
+  pub fn root(mut self, root: &str) -> Self {
+    self.storage.root = Some(root.to_string());
+    self
+  }
+
+  /// 重试、超时与带宽限制配置
+  pub fn transfer(mut self, transfer: TransferConf) -> Self {
+    self.transfer = transfer;
+    self
+  }
+
+  pub fn build(self) -> Result<StorageClient, StorageError> {
+    let client = || {
+      let op = with_transfer_layers(build_operator(&self.service, &self.storage)?, &self.transfer);
+      let http = HttpClient::new(&self.transfer)?;
+      let multipart = MultipartUploader::new(&self.service, &self.storage, &http, &self.transfer)?.map(Arc::new);
+      let journal_dir = self.transfer.journal_dir.as_deref().map(expand_home);
+      Ok(StorageClient { op, http: Some(http), multipart, journal_dir, progress: false })
+    };
+    client().map_err(StorageError::Config)
+  }
+}
@@ -0,0 +1,223 @@
+use std::{path::Path, time::Duration};
+
+use anyhow::Result;
+use crc::{CRC_64_XZ, Crc};
+use log::{info, warn};
+use md5::Md5;
+use opendal::{Metadata, Operator, Scheme};
+use reqwest::header::HeaderMap;
+use sha2::{Digest, Sha256};
+use tokio::{fs::File, io::AsyncReadExt};
+
+use super::http::HttpClient;
+use crate::{client::StorageClient, error::StorageError};
+
+/// OSS、COS 返回的 CRC64 使用 ECMA-182 多项式，即 CRC-64/XZ
+static CRC64: Crc<u64> = Crc::<u64>::new(&CRC_64_XZ);
+
+/// 传输过程中流式计算 MD5、SHA-256 与 CRC64
+pub(super) struct Hasher {
+  md5: Md5,
+  sha256: Sha256,
+  crc64: crc::Digest<'static, u64>,
+}
+
+impl Default for Hasher {
+  fn default() -> Self {
+    Self { md5: Md5::new(), sha256: Sha256::new(), crc64: CRC64.digest() }
+  }
+}
+
+impl Hasher {
+  pub(super) fn update(&mut self, data: &[u8]) {
+    self.md5.update(data);
+    self.sha256.update(data);
+    self.crc64.update(data);
+  }
+
+  pub(super) fn finish(self) -> Checksums {
+    Checksums {
+      md5: format!("{:x}", self.md5.finalize()),
+      sha256: format!("{:x}", self.sha256.finalize()),
+      crc64: self.crc64.finalize(),
+    }
+  }
+}
+
+/// 已传输内容的校验和，md5 与 sha256 为十六进制小写
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Checksums {
+  pub(super) md5: String,
+  pub(super) sha256: String,
+  pub(super) crc64: u64,
+}
+
+/// 计算本地文件的校验和
+pub(super) async fn file_checksums(path: &Path) -> Result<Checksums> {
+  let mut f = File::open(path).await?;
+  let mut hasher = Hasher::default();
+  let mut buf = vec![0_u8; 64 * 1024];
+  loop {
+    let n = f.read(&mut buf[..]).await?;
+    if n == 0 {
+      break;
+    }
+    hasher.update(&buf[..n]);
+  }
+  Ok(hasher.finish())
+}
+
+/// 传输完成后校验对象的完整性：比较对象的 MD5（content-md5 或已知为 MD5 的 etag）与 OSS、COS 的 CRC64，
+/// 任一不一致时返回错误。存储未提供任何校验和时仅输出警告
+pub(super) async fn verify_object(client: &StorageClient, object_key: &str, local: &Checksums) -> Result<()> {
+  let op = client.operator();
+  let md = op.stat(object_key).await?;
+  let scheme = op.info().scheme();
+  let headers = match client.http() {
+    Some(http) => remote_headers(op, http, object_key).await,
+    None => None,
+  };
+  let mut verified = Vec::new();
+
+  let remote_md5 = content_md5_hex(&md).or_else(|| etag_is_md5(scheme, headers.as_ref()).then(|| etag_md5_hex(&md))?);
+  if let Some(remote) = remote_md5 {
+    if remote != local.md5 {
+      return Err(mismatch(object_key, "md5", remote, local.md5.clone()));
+    }
+    verified.push("md5");
+  }
+  if let Some(remote) = remote_crc64(scheme, headers.as_ref()) {
+    if remote != local.crc64 {
+      return Err(mismatch(object_key, "crc64", remote.to_string(), local.crc64.to_string()));
+    }
+    verified.push("crc64");
+  }
+
+  if verified.is_empty() {
+    warn!("No checksum of '{}' is provided by the storage, verification skipped, sha256: {}", object_key, local.sha256);
+  } else {
+    info!("The object '{}' is verified by {}, sha256: {}", object_key, verified.join(" and "), local.sha256);
+  }
+  Ok(())
+}
+
+fn mismatch(object_key: &str, algorithm: &'static str, remote: String, local: String) -> anyhow::Error {
+  StorageError::ChecksumMismatch { path: object_key.to_string(), algorithm, remote, local }.into()
+}
+
+/// 从列举或查询的元数据中获取十六进制的 MD5，用于 sync 判断文件是否变更：优先使用 content-md5，OBS、S3 使用
+/// 单段上传的 etag。无法确认对象是否使用 SSE-KMS 加密（此时 etag 不是 MD5），误判时仅导致重新传输
+pub(super) fn remote_md5_hex(md: &Metadata, scheme: Scheme) -> Option<String> {
+  content_md5_hex(md).or_else(|| matches!(scheme, Scheme::Obs | Scheme::S3).then(|| etag_md5_hex(md))?)
+}
+
+fn content_md5_hex(md: &Metadata) -> Option<String> {
+  use base64::Engine;
+
+  let bytes = base64::engine::general_purpose::STANDARD.decode(md.content_md5()?).ok()?;
+  Some(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// 格式为 MD5 的 etag。分段上传的 etag（如 `"<md5>-3"`）不是文件 MD5，此时返回 `None`
+fn etag_md5_hex(md: &Metadata) -> Option<String> {
+  let etag = md.etag()?.trim_start_matches("W/").trim_matches('"').to_ascii_lowercase();
+  (etag.len() == 32 && etag.chars().all(|c| c.is_ascii_hexdigit())).then_some(etag)
+}
+
+/// etag 是否为对象内容的 MD5：仅 OBS、S3 未使用 SSE-KMS、SSE-C 加密的对象是。OSS 的追加写对象、加密对象的 etag
+/// 不是 MD5，OSS、COS 改用 CRC64 校验；Azure Blob、GCS 等的 etag 与内容无关
+fn etag_is_md5(scheme: Scheme, headers: Option<&HeaderMap>) -> bool {
+  let Some(headers) = headers.filter(|_| matches!(scheme, Scheme::Obs | Scheme::S3)) else {
+    return false;
+  };
+  let kms = ["x-amz-server-side-encryption", "x-obs-server-side-encryption"]
+    .iter()
+    .filter_map(|name| headers.get(*name))
+    .any(|v| v.as_bytes().starts_with(b"aws:kms") || v == "kms");
+  let sse_c = ["x-amz-server-side-encryption-customer-algorithm", "x-obs-server-side-encryption-customer-algorithm"]
+    .iter()
+    .any(|name| headers.contains_key(*name));
+  !kms && !sse_c
+}
+
+/// 获取对象的响应头。opendal 的 Metadata 不包含加密方式与 CRC64，通过预签名的 HEAD 请求获取，仅用于 OBS、OSS、
+/// S3、COS。请求使用 StorageClient 的 [`HttpClient`]，按 `[transfer]` 配置超时与重试
+async fn remote_headers(op: &Operator, http: &HttpClient, object_key: &str) -> Option<HeaderMap> {
+  if !matches!(op.info().scheme(), Scheme::Obs | Scheme::Oss | Scheme::S3 | Scheme::Cos) {
+    return None;
+  }
+  let req = op.presign_stat(object_key, Duration::from_secs(300)).await.ok()?;
+  let send = http.send(|client| Ok(client.head(req.uri().to_string()).headers(req.header().clone()).build()?));
+  match send.await {
+    Ok(resp) if resp.status().is_success() => Some(resp.headers().clone()),
+    Ok(resp) => {
+      warn!("Fetch the headers of '{}' failed: {}", object_key, resp.status());
+      None
+    }
+    Err(e) => {
+      warn!("Fetch the headers of '{}' failed: {}", object_key, e);
+      None
+    }
+  }
+}
+
+/// OSS、COS 对象的 CRC64，分段上传的对象同样提供
+fn remote_crc64(scheme: Scheme, headers: Option<&HeaderMap>) -> Option<u64> {
+  let header = match scheme {
+    Scheme::Oss => "x-oss-hash-crc64ecma",
+    Scheme::Cos => "x-cos-hash-crc64ecma",
+    _ => return None,
+  };
+  headers?.get(header)?.to_str().ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_hasher() {
+    let mut hasher = Hasher::default();
+    hasher.update(b"12345");
+    hasher.update(b"6789");
+    let checksums = hasher.finish();
+    assert_eq!(checksums.md5, "25f9e794323b453885f5181f1b624d0b");
+    assert_eq!(checksums.sha256, "15e2b0d3c33891ebb0f1ef609ec419420c20e320ce94c65fbc8c3312448eb225");
+    assert_eq!(checksums.crc64, 0x995dc9bbdf1939fa);
+  }
+
+  #[test]
+  fn test_remote_md5_hex() {
+    let md = Metadata::new(opendal::EntryMode::FILE).with_etag("\"25F9E794323B453885F5181F1B624D0B\"".into());
+    assert_eq!(remote_md5_hex(&md, Scheme::Obs).as_deref(), Some("25f9e794323b453885f5181f1b624d0b"));
+    // OSS 的追加写对象与加密对象的 etag 不是 MD5
+    assert_eq!(remote_md5_hex(&md, Scheme::Oss), None);
+    let md = Metadata::new(opendal::EntryMode::FILE).with_etag("\"25f9e794323b453885f5181f1b624d0b-3\"".into());
+    assert_eq!(remote_md5_hex(&md, Scheme::S3), None);
+    let md = Metadata::new(opendal::EntryMode::FILE).with_content_md5("JfnnlDI7RTiF9RgfG2JNCw==".into());
+    assert_eq!(remote_md5_hex(&md, Scheme::Azblob).as_deref(), Some("25f9e794323b453885f5181f1b624d0b"));
+  }
+
+  #[test]
+  fn test_etag_is_md5() {
+    let mut headers = HeaderMap::new();
+    assert!(etag_is_md5(Scheme::Obs, Some(&headers)));
+    assert!(!etag_is_md5(Scheme::Obs, None));
+    assert!(!etag_is_md5(Scheme::Oss, Some(&headers)));
+    headers.insert("x-amz-server-side-encryption", "AES256".parse().unwrap());
+    assert!(etag_is_md5(Scheme::S3, Some(&headers)));
+    headers.insert("x-amz-server-side-encryption", "aws:kms".parse().unwrap());
+    assert!(!etag_is_md5(Scheme::S3, Some(&headers)));
+    let mut headers = HeaderMap::new();
+    headers.insert("x-obs-server-side-encryption", "kms".parse().unwrap());
+    assert!(!etag_is_md5(Scheme::Obs, Some(&headers)));
+  }
+
+  #[test]
+  fn test_remote_crc64() {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-oss-hash-crc64ecma", "11051210869376104954".parse().unwrap());
+    assert_eq!(remote_crc64(Scheme::Oss, Some(&headers)), Some(0x995dc9bbdf1939fa));
+    assert_eq!(remote_crc64(Scheme::Cos, Some(&headers)), None);
+  }
+}
@@ -0,0 +1,137 @@
+use std::{io::Write, path::Path};
+
+use anyhow::{Result, bail};
+use clap::{Subcommand, ValueEnum};
+use config::{Source, Value, ValueKind};
+
+use super::{DevopsCmd, StorageSource};
+use crate::{
+  conf::DevopsConf,
+  utils::{confirm, mask_secret, prompt},
+};
+
+/// `config show` 输出的顶层配置项
+const SHOWN_KEYS: &[&str] = &["service", "storage", "storages", "transfer"];
+
+/// `config show` 中需要掩码的配置项
+const SECRET_KEYS: &[&str] = &["ak", "sk", "security_token"];
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigOperation {
+  /// 输出已加载的配置文件，按优先级从低到高排列
+  Path,
+  /// 输出合并后生效的配置及每项配置的来源，ak、sk 与 security_token 将被掩码
+  Show,
+  /// 检查配置的必填项与 endpoint 格式，不发起网络请求
+  Validate,
+  /// 交互式生成配置文件
+  Init {
+    /// 生成的配置文件路径
+    #[arg(long, default_value = ".devops-cli.toml")]
+    path: String,
+    /// 覆盖已存在的配置文件
+    #[arg(long)]
+    force: bool,
+  },
+}
+
+impl ConfigOperation {
+  pub fn execute(&self, cmd: &DevopsCmd) -> Result<()> {
+    match self {
+      ConfigOperation::Path => {
+        let (_, config_files) = DevopsConf::merged_config(cmd)?;
+        for file in config_files {
+          println!("{}", file.display());
+        }
+      }
+      ConfigOperation::Show => {
+        let (c, _) = DevopsConf::merged_config(cmd)?;
+        let table = c.collect()?;
+        let mut lines = Vec::new();
+        for key in SHOWN_KEYS {
+          if let Some(value) = table.get(*key) {
+            flatten_value(key, value, &mut lines);
+          }
+        }
+        for (key, value, origin) in lines {
+          println!("{} = {}  # {}", key, value, origin);
+        }
+      }
+      ConfigOperation::Validate => {
+        let problems = match DevopsConf::from_devops_cmd(cmd) {
+          Ok(conf) => conf.validate(),
+          Err(e) => vec![e.to_string()],
+        };
+        if !problems.is_empty() {
+          for problem in problems.iter() {
+            println!("{}", problem);
+          }
+          bail!("The config is invalid, {} problems found", problems.len());
+        }
+        println!("The config is valid.");
+      }
+      ConfigOperation::Init { path, force } => init_config_file(Path::new(path), *force)?,
+    }
+    Ok(())
+  }
+}
+
+/// 将嵌套的配置展开为 `(key, value, origin)` 列表，key 以 `.` 连接
+fn flatten_value(key: &str, value: &Value, lines: &mut Vec<(String, String, String)>) {
+  match &value.kind {
+    ValueKind::Table(table) => {
+      let mut keys = table.keys().collect::<Vec<_>>();
+      keys.sort();
+      for k in keys {
+        flatten_value(&format!("{}.{}", key, k), &table[k], lines);
+      }
+    }
+    kind => {
+      let leaf = key.rsplit('.').next().unwrap_or(key);
+      let v = if SECRET_KEYS.contains(&leaf) { mask_secret(&kind.to_string()) } else { kind.to_string() };
+      lines.push((key.to_string(), v, value.origin().unwrap_or("default").to_string()));
+    }
+  }
+}
+
+/// 交互式生成配置文件，格式同 `examples/app-template.toml`
+fn init_config_file(path: &Path, force: bool) -> Result<()> {
+  if path.exists() && !force && !confirm(&format!("The file '{}' already exists, overwrite?", path.display()))? {
+    return Ok(());
+  }
+
+  let services = StorageSource::value_variants().iter().map(|s| s.to_string()).collect::<Vec<_>>();
+  let service = prompt(&format!("service ({})", services.join(", ")), Some(&StorageSource::Obs.to_string()))?;
+  if StorageSource::from_str(&service, true).is_err() {
+    bail!("Unsupported service '{}'", service);
+  }
+  let endpoint = prompt("endpoint", None)?;
+  let bucket = prompt("bucket", None)?;
+  let ak = prompt("ak", None)?;
+  let sk = prompt("sk", None)?;
+
+  let content = format!(
+    "service = {}\n\n[storage]\nendpoint = {}\nbucket = {}\nak = {}\nsk = {}\n",
+    toml_quote(&service),
+    toml_quote(&endpoint),
+    toml_quote(&bucket),
+    toml_quote(&ak),
+    toml_quote(&sk),
+  );
+
+  let mut options = std::fs::OpenOptions::new();
+  options.write(true).create(true).truncate(true);
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::OpenOptionsExt;
+    // 配置文件包含 sk，仅允许当前用户读写
+    options.mode(0o600);
+  }
+  options.open(path)?.write_all(content.as_bytes())?;
+  println!("The config file '{}' is written.", path.display());
+  Ok(())
+}
+
+fn toml_quote(s: &str) -> String {
+  format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
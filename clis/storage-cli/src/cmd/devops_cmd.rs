@@ -3,9 +3,10 @@ use std::fmt::Display;
 use clap::{Parser, ValueEnum};
 use serde::Deserialize;
 
-use super::FileOperation;
+use super::{FileOperation, OutputFormat};
+use crate::utils::{mask_secret, parse_size};
 
-#[derive(Debug, Default, Parser)]
+#[derive(Default, Parser)]
 #[command(name = "devops-cli")]
 #[command(version, about = "DevOps command tool")]
 pub struct DevopsCmd {
@@ -24,16 +25,89 @@ pub struct DevopsCmd {
   #[arg(short('f'), long)]
   pub config_file: Option<String>,
 
+  #[arg(short, long, help = "The profile in config file '[profiles.<name>]' to use, or env 'DEVOPS_PROFILE'")]
+  pub profile: Option<String>,
+
+  #[arg(short, long, global = true, value_enum, default_value_t = OutputFormat::Table, help = "Output format")]
+  pub output: OutputFormat,
+
+  #[arg(long, global = true, help = "Do not show progress bars, disabled automatically when stderr is not a terminal")]
+  pub no_progress: bool,
+
+  #[arg(long, global = true, help = "Max retries of a failed request, overrides 'transfer.max_retries'")]
+  pub retries: Option<usize>,
+
+  #[arg(long, global = true, help = "Timeout seconds of a request, overrides 'transfer.timeout'")]
+  pub timeout: Option<u64>,
+
+  #[arg(
+    long,
+    global = true,
+    value_parser = parse_size,
+    help = "Max bytes per second, e.g. 10MiB, overrides 'transfer.bandwidth'"
+  )]
+  pub bandwidth: Option<usize>,
+
   #[command(subcommand)]
   pub file_op: Option<FileOperation>,
 }
 
+impl std::fmt::Debug for DevopsCmd {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("DevopsCmd")
+      .field("service", &self.service)
+      .field("bucket", &self.bucket)
+      .field("ak", &self.ak.as_deref().map(mask_secret))
+      .field("sk", &self.sk.as_deref().map(mask_secret))
+      .field("config_file", &self.config_file)
+      .field("profile", &self.profile)
+      .field("output", &self.output)
+      .field("no_progress", &self.no_progress)
+      .field("retries", &self.retries)
+      .field("timeout", &self.timeout)
+      .field("bandwidth", &self.bandwidth)
+      .field("file_op", &self.file_op)
+      .finish()
+  }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, ValueEnum, Deserialize)]
 pub enum StorageSource {
   /// 华为云 OBS
   Obs,
   /// 阿里云 OSS
   Oss,
+  /// AWS S3 及兼容 S3 协议的存储，如 MinIO
+  #[cfg(feature = "services-s3")]
+  S3,
+  /// 腾讯云 COS
+  #[cfg(feature = "services-cos")]
+  Cos,
+  /// Azure Blob Storage
+  #[cfg(feature = "services-azblob")]
+  Azblob,
+  /// Google Cloud Storage
+  #[cfg(feature = "services-gcs")]
+  Gcs,
+  /// 本地文件系统
+  #[cfg(feature = "services-fs")]
+  Fs,
+  /// 内存存储，数据仅在同一个 Operator 内有效，用于测试
+  #[cfg(feature = "services-memory")]
+  Memory,
+}
+
+impl StorageSource {
+  /// 是否支持 STS 临时凭证的 security token，Azure Blob 与 GCS 使用其它的临时凭证机制
+  pub fn supports_security_token(&self) -> bool {
+    match self {
+      #[cfg(feature = "services-azblob")]
+      StorageSource::Azblob => false,
+      #[cfg(feature = "services-gcs")]
+      StorageSource::Gcs => false,
+      _ => true,
+    }
+  }
 }
 
 impl Display for StorageSource {
@@ -41,6 +115,18 @@ impl Display for StorageSource {
     match self {
       StorageSource::Obs => f.write_str("obs"),
       StorageSource::Oss => f.write_str("oss"),
+      #[cfg(feature = "services-s3")]
+      StorageSource::S3 => f.write_str("s3"),
+      #[cfg(feature = "services-cos")]
+      StorageSource::Cos => f.write_str("cos"),
+      #[cfg(feature = "services-azblob")]
+      StorageSource::Azblob => f.write_str("azblob"),
+      #[cfg(feature = "services-gcs")]
+      StorageSource::Gcs => f.write_str("gcs"),
+      #[cfg(feature = "services-fs")]
+      StorageSource::Fs => f.write_str("fs"),
+      #[cfg(feature = "services-memory")]
+      StorageSource::Memory => f.write_str("memory"),
     }
   }
 }
@@ -1,76 +1,938 @@
-use anyhow::Result;
-use clap::Subcommand;
-use futures::TryStreamExt;
-use log::info;
-use opendal::Operator;
-use tokio::{fs::File, io::AsyncReadExt};
+use std::{
+  future::Future,
+  io::IsTerminal,
+  ops::Range,
+  path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::{Args, Subcommand};
+use futures::{StreamExt, TryStreamExt};
+use log::{error, info, warn};
+use opendal::{Operator, Writer, operator_futures::FutureWriter};
+use serde::Serialize;
+use tokio::{
+  fs::File,
+  io::{AsyncRead, AsyncReadExt, AsyncWrite},
+};
+
+use super::{
+  ConfigOperation, MultipartUploader, PresignOperation,
+  checksum::{Checksums, Hasher, file_checksums, verify_object},
+  output::{is_structured, print_structured},
+  preview::{ByteRange, cat_object_key, head_object_key, resolve_range, tail_object_key, write_range_to},
+  progress::Progress,
+  resume::{get_resume, multipart_upload, put_resume},
+  sync::{SyncDirection, sync_dir},
+};
+use crate::{
+  client::{DirTransferResult, StorageClient, TransferResult},
+  conf::DevopsConf,
+  error::StorageError,
+  operators::{get_named_operator, get_operator},
+  utils::{confirm, parse_meta, parse_size},
+};
+
+/// 上传时每次从本地文件读取的字节数
+pub(super) const READ_BUF_SIZE: usize = 1024 * 1024;
+
+/// `put` 的 src 或 `get` 的 dst 为 `-` 时表示标准输入或标准输出
+const STDIO_PATH: &str = "-";
+
+/// 上传选项，大文件按 `chunk_size` 分段并发上传
+#[derive(Debug, Clone, Args)]
+pub struct UploadOptions {
+  /// 分段上传的段大小，支持 K/KiB、M/MiB、G/GiB 等单位
+  #[arg(long, value_parser = parse_size, default_value = "8MiB")]
+  pub chunk_size: usize,
+  /// 单个文件并发上传的段数
+  #[arg(long, default_value_t = 4)]
+  pub concurrency: usize,
+  /// 对象的 Content-Type，未设置时按文件扩展名推断
+  #[arg(long)]
+  pub content_type: Option<String>,
+  /// 对象的 Cache-Control，如 `max-age=3600`
+  #[arg(long)]
+  pub cache_control: Option<String>,
+  /// 对象的 Content-Disposition，如 `attachment; filename="app.tar.gz"`
+  #[arg(long)]
+  pub content_disposition: Option<String>,
+  /// 对象的自定义元数据，格式为 `key=value`，可多次指定。仅 OBS、OSS、S3、COS 支持，不支持从标准输入上传
+  #[arg(long = "meta", value_name = "KEY=VALUE", value_parser = parse_meta)]
+  pub meta: Vec<(String, String)>,
+}
+
+impl Default for UploadOptions {
+  fn default() -> Self {
+    Self {
+      chunk_size: 8 * 1024 * 1024,
+      concurrency: 4,
+      content_type: None,
+      cache_control: None,
+      content_disposition: None,
+      meta: Vec::new(),
+    }
+  }
+}
+
+impl UploadOptions {
+  /// 上传 src 时对象的 Content-Type：优先使用 `--content-type`，否则按文件扩展名推断，无法推断时为 `None`
+  fn content_type_of(&self, src: &str) -> Option<String> {
+    self.content_type.clone().or_else(|| {
+      (src != STDIO_PATH).then(|| mime_guess::from_path(src).first_raw()).flatten().map(ToString::to_string)
+    })
+  }
+
+  /// 通过 [`MultipartUploader`] 上传时设置的对象 Content-Type、Cache-Control、Content-Disposition 与自定义元数据
+  /// 请求头
+  pub(super) fn headers_of(&self, src: &str, mp: &MultipartUploader) -> Vec<(String, String)> {
+    let mut headers = Vec::new();
+    if let Some(v) = self.content_type_of(src) {
+      headers.push(("content-type".to_string(), v));
+    }
+    if let Some(v) = self.cache_control.clone() {
+      headers.push(("cache-control".to_string(), v));
+    }
+    if let Some(v) = self.content_disposition.clone() {
+      headers.push(("content-disposition".to_string(), v));
+    }
+    for (key, value) in self.meta.iter() {
+      headers.push((format!("{}{}", mp.meta_prefix(), key), value.clone()));
+    }
+    headers
+  }
+
+  /// 为写入设置对象的 Content-Type、Cache-Control 与 Content-Disposition，存储不支持时忽略，明确指定的选项
+  /// 被忽略时输出警告
+  pub(super) fn apply<F: Future<Output = opendal::Result<Writer>>>(
+    &self,
+    op: &Operator,
+    src: &str,
+    mut fw: FutureWriter<F>,
+  ) -> FutureWriter<F> {
+    let cap = op.info().full_capability();
+    let ignored = |name: &str| warn!("The storage '{}' does not support {}, ignored.", op.info().scheme(), name);
+    if let Some(v) = self.content_type_of(src) {
+      if cap.write_with_content_type {
+        fw = fw.content_type(&v);
+      } else if self.content_type.is_some() {
+        ignored("Content-Type");
+      }
+    }
+    if let Some(v) = self.cache_control.as_deref() {
+      if cap.write_with_cache_control {
+        fw = fw.cache_control(v);
+      } else {
+        ignored("Cache-Control");
+      }
+    }
+    if let Some(v) = self.content_disposition.as_deref() {
+      if cap.write_with_content_disposition {
+        fw = fw.content_disposition(v);
+      } else {
+        ignored("Content-Disposition");
+      }
+    }
+    // opendal 的写入不支持自定义元数据，可以设置时已通过 MultipartUploader 上传
+    if !self.meta.is_empty() {
+      ignored("user metadata");
+    }
+    fw
+  }
+}
 
 #[derive(Debug, Subcommand)]
 pub enum FileOperation {
-  Put { src: String, object_key: String },
-  Get { object_key: String, dst: String },
+  /// 上传文件，src 为 `-` 时从标准输入读取
+  Put {
+    src: String,
+    object_key: String,
+    /// 递归上传目录，object_key 作为对象前缀
+    #[arg(short, long)]
+    recursive: bool,
+    /// 递归上传时并发上传的文件数
+    #[arg(short, long, default_value_t = 4)]
+    jobs: usize,
+    /// 断点续传，分段上传并在本地记录已完成的段，需要存储支持分段上传（OBS、OSS、S3、COS）
+    #[arg(long, conflicts_with = "recursive")]
+    resume: bool,
+    #[command(flatten)]
+    upload: UploadOptions,
+  },
+  /// 下载文件，dst 为 `-` 时输出到标准输出
+  Get {
+    object_key: String,
+    dst: String,
+    /// 递归下载 object_key 前缀下的所有对象到 dst 目录
+    #[arg(short, long)]
+    recursive: bool,
+    /// 递归下载时并发下载的文件数
+    #[arg(short, long, default_value_t = 4)]
+    jobs: usize,
+    /// 覆盖本地已存在的文件
+    #[arg(long, conflicts_with = "skip_existing")]
+    overwrite: bool,
+    /// 跳过本地已存在的文件
+    #[arg(long)]
+    skip_existing: bool,
+    /// 断点续传，本地文件为同一对象的部分内容时继续下载。本地文件已存在但没有续传日志时失败，同时指定
+    /// --overwrite 时重新下载
+    #[arg(long, conflicts_with_all = ["recursive", "skip_existing"])]
+    resume: bool,
+    /// 仅下载指定的字节范围，格式为 `start-end`（包含 end）、`start-` 或 `-n`（最后 n 字节）
+    #[arg(long, conflicts_with_all = ["recursive", "resume"])]
+    range: Option<ByteRange>,
+  },
   Stat { object_key: String },
+  /// 输出对象内容到标准输出
+  Cat {
+    object_key: String,
+    /// 仅输出指定的字节范围，格式同 `get --range`
+    #[arg(long)]
+    range: Option<ByteRange>,
+  },
+  /// 输出对象的前若干行或前若干字节，不下载整个对象
+  Head {
+    object_key: String,
+    /// 输出的行数
+    #[arg(short = 'n', long, default_value_t = 10)]
+    lines: usize,
+    /// 输出的字节数，指定时忽略 --lines
+    #[arg(short = 'c', long)]
+    bytes: Option<u64>,
+  },
+  /// 输出对象的最后若干字节，仅读取对象末尾的内容
+  Tail {
+    object_key: String,
+    /// 输出的字节数
+    #[arg(short = 'c', long, default_value_t = 1024)]
+    bytes: u64,
+  },
+  /// 在本地目录与对象存储前缀之间单向增量同步，仅传输有变更的文件
+  Sync {
+    local: String,
+    prefix: String,
+    /// 同步方向：up 为本地到对象存储，down 为对象存储到本地
+    #[arg(short, long, value_enum, default_value_t = SyncDirection::Up)]
+    direction: SyncDirection,
+    /// 删除目标端存在而源端不存在的文件
+    #[arg(long)]
+    delete: bool,
+    /// 跳过 --delete 的交互式确认，标准输入不是终端时必须指定
+    #[arg(short, long)]
+    yes: bool,
+    /// 仅输出同步计划，不实际传输或删除
+    #[arg(long)]
+    dry_run: bool,
+    /// 并发传输的文件数
+    #[arg(short, long, default_value_t = 4)]
+    jobs: usize,
+    #[command(flatten)]
+    upload: UploadOptions,
+  },
+  /// 复制对象，后端不支持服务端复制时经由本程序流式复制。
+  ///
+  /// 使用 `<name>:<key>` 指定 `[storages.<name>]` 中配置的存储，可在不同 bucket 或不同云厂商之间复制
+  #[command(visible_alias = "cp")]
+  Copy { src_key: String, dst_key: String },
+  /// 移动（重命名）对象，后端不支持服务端重命名时复制后删除源对象
+  #[command(visible_alias = "mv")]
+  Move { src_key: String, dst_key: String },
+  /// 删除对象，递归时删除前缀下的所有对象
+  #[command(visible_alias = "rm")]
+  Delete {
+    object_key: String,
+    /// 递归删除 object_key 前缀下的所有对象
+    #[arg(short, long)]
+    recursive: bool,
+    /// 跳过交互式确认，标准输入不是终端时必须指定
+    #[arg(short, long)]
+    yes: bool,
+    /// 仅列出将被删除的对象，不实际删除
+    #[arg(long)]
+    dry_run: bool,
+  },
+  /// 配置相关命令：path、show、validate、init
+  Config {
+    #[command(subcommand)]
+    op: ConfigOperation,
+  },
+  /// 生成对象的预签名 URL，无需凭证即可在有效期内下载或上传
+  Presign {
+    #[command(subcommand)]
+    op: PresignOperation,
+  },
+  /// 列出指定前缀下的对象
+  #[command(visible_alias = "ls")]
+  List {
+    #[arg(default_value = "/")]
+    prefix: String,
+    /// 递归列出所有子目录下的对象
+    #[arg(short, long)]
+    recursive: bool,
+    /// 最多列出的对象数量，同时作为每页请求的数量
+    #[arg(short, long)]
+    limit: Option<usize>,
+  },
 }
 
 impl FileOperation {
-  pub async fn execute(&self, op: &Operator) -> Result<()> {
+  pub async fn execute(&self, conf: &DevopsConf) -> Result<()> {
+    if let FileOperation::Copy { src_key, dst_key } = self {
+      return copy_between_storages(conf, src_key, dst_key).await;
+    }
+
+    let client = StorageClient::new(conf).await?.with_progress(true);
+    self.execute_with_client(&client).await
+  }
+
+  /// 使用给定的 Operator 执行，`cp` 命令不解析 `<name>:<key>` 形式的命名存储
+  pub async fn execute_with_operator(&self, op: &Operator) -> Result<()> {
+    self.execute_with_client(&StorageClient::from_operator(op.clone()).with_progress(true)).await
+  }
+
+  /// 使用给定的 StorageClient 执行，结果输出到标准输出
+  pub async fn execute_with_client(&self, client: &StorageClient) -> Result<()> {
+    self.execute_with_output(client, &mut tokio::io::stdout()).await
+  }
+
+  /// 使用给定的 StorageClient 执行，cat、head、tail 与 `get -` 的对象内容写入 out，其余结果输出到标准输出
+  pub async fn execute_with_output<W: AsyncWrite + Unpin>(&self, client: &StorageClient, out: &mut W) -> Result<()> {
+    let op = client.operator();
     match self {
-      FileOperation::Put { src, object_key } => put_src_to_object_key(op, src, object_key).await?,
-      FileOperation::Get { object_key, dst } => get_object_key_to_dst(op, object_key, dst).await?,
-      FileOperation::Stat { object_key } => dump_stat(op, object_key).await?,
+      FileOperation::Put { src, recursive, resume, .. } if src == STDIO_PATH && (*recursive || *resume) => {
+        bail!("Uploading from stdin does not support --recursive or --resume")
+      }
+      FileOperation::Get { dst, recursive, resume, .. } if dst == STDIO_PATH && (*recursive || *resume) => {
+        bail!("Downloading to stdout does not support --recursive or --resume")
+      }
+      FileOperation::Put { src, object_key, recursive: true, jobs, upload, .. } => {
+        TransferSummary::of_dir(client.upload_dir(src, object_key, *jobs, upload).await?).finish()?
+      }
+      FileOperation::Put { src, object_key, resume: true, upload, .. } => {
+        print_structured(&put_resume(client, src, object_key, upload).await?)?;
+      }
+      FileOperation::Put { src, object_key, upload, .. } if src == STDIO_PATH => {
+        let progress = Progress::new(object_key, None);
+        let result = put_src_to_object_key(client, src, object_key, upload, &progress).await;
+        progress.finish();
+        print_structured(&result?)?;
+      }
+      FileOperation::Put { src, object_key, upload, .. } => {
+        print_structured(&client.upload_file_with(src, object_key, upload).await?)?;
+      }
+      FileOperation::Get { object_key, dst, recursive: true, jobs, overwrite, skip_existing, .. } => {
+        let policy = ExistingPolicy::new(*overwrite, *skip_existing);
+        TransferSummary::of_dir(client.download_dir(object_key, dst, *jobs, policy).await?).finish()?
+      }
+      FileOperation::Get { object_key, dst, resume: true, overwrite, .. } => {
+        print_structured(&get_resume(client, object_key, dst, *overwrite).await?)?;
+      }
+      FileOperation::Get { object_key, dst, range, .. } if dst == STDIO_PATH => {
+        let mut hasher = range.is_none().then(Hasher::default);
+        let range = resolve_range(op, object_key, *range).await?;
+        let progress = Progress::new(object_key, Some(range.end - range.start));
+        let n = write_range_to(op, object_key, range, out, hasher.as_mut(), &progress).await?;
+        info!("Total file download of {} bytes.", n);
+        if let Some(hasher) = hasher {
+          verify_object(client, object_key, &hasher.finish()).await?;
+        }
+      }
+      FileOperation::Get { object_key, dst, overwrite, skip_existing, range, .. } => {
+        let policy = ExistingPolicy::new(*overwrite, *skip_existing);
+        match client.download_file_with(object_key, dst, policy, *range).await? {
+          Some(result) => {
+            print_structured(&result)?;
+          }
+          None => info!("The file '{}' already exists, skipped.", dst),
+        }
+      }
+      FileOperation::Stat { object_key } => dump_stat(client, object_key).await?,
+      FileOperation::Cat { object_key, range } => cat_object_key(op, object_key, *range, out).await?,
+      FileOperation::Head { object_key, lines, bytes } => head_object_key(op, object_key, *lines, *bytes, out).await?,
+      FileOperation::Tail { object_key, bytes } => tail_object_key(op, object_key, *bytes, out).await?,
+      FileOperation::Sync { local, prefix, direction, delete, yes, dry_run, jobs, upload } => {
+        sync_dir(client, local, prefix, *direction, *delete, *yes, *dry_run, *jobs, upload).await?
+      }
+      FileOperation::Copy { src_key, dst_key } => copy_object_key(op, src_key, dst_key).await?,
+      FileOperation::Config { .. } => bail!("The config command should be executed by ConfigOperation::execute"),
+      FileOperation::Presign { op: presign } => presign.execute(op).await?,
+      FileOperation::Move { src_key, dst_key } => move_object_key(op, src_key, dst_key).await?,
+      FileOperation::Delete { object_key, recursive, yes, dry_run } => {
+        delete_object_key(client, object_key, *recursive, *yes, *dry_run).await?
+      }
+      FileOperation::List { prefix, recursive, limit } => list_prefix(client, prefix, *recursive, *limit).await?,
     }
     Ok(())
   }
 }
 
-/// 上传本地文件到对象存储
-async fn put_src_to_object_key(op: &Operator, src: &str, object_key: &str) -> Result<()> {
-  use futures::AsyncWriteExt;
+/// 本地文件已存在时的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExistingPolicy {
+  /// 返回错误
+  Fail,
+  /// 覆盖已存在的文件
+  Overwrite,
+  /// 跳过已存在的文件
+  Skip,
+}
+
+impl ExistingPolicy {
+  fn new(overwrite: bool, skip_existing: bool) -> Self {
+    if overwrite {
+      ExistingPolicy::Overwrite
+    } else if skip_existing {
+      ExistingPolicy::Skip
+    } else {
+      ExistingPolicy::Fail
+    }
+  }
+}
+
+/// 批量传输的汇总结果，`sync --delete` 时包含删除的文件数
+#[derive(Debug, Default, Serialize)]
+pub(super) struct TransferSummary {
+  files: usize,
+  bytes: u64,
+  skipped: usize,
+  failures: usize,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  deleted: Option<usize>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  delete_failures: Option<usize>,
+}
+
+impl TransferSummary {
+  /// 包含删除结果的汇总
+  pub(super) fn with_deletes() -> Self {
+    Self { deleted: Some(0), delete_failures: Some(0), ..Default::default() }
+  }
 
-  let mut f = File::open(src).await?;
-  let mut writer = op.writer_with(object_key).await?.into_futures_async_write();
-  let mut buf = [0_u8; 8192];
+  /// 汇总目录传输的结果，输出失败文件的错误
+  fn of_dir(result: DirTransferResult) -> Self {
+    let mut summary = Self::default();
+    for r in result.transferred {
+      summary.add(Ok(r.bytes), &r.object_key);
+    }
+    for object_key in result.skipped {
+      summary.skip(&object_key);
+    }
+    for (object_key, e) in result.failed {
+      summary.add(Err(e.into()), &object_key);
+    }
+    summary
+  }
+
+  pub(super) fn add(&mut self, result: Result<u64>, path: &str) {
+    match result {
+      Ok(n) => {
+        self.files += 1;
+        self.bytes += n;
+      }
+      Err(e) => {
+        error!("Transfer '{}' failed: {}", path, e);
+        self.failures += 1;
+      }
+    }
+  }
+
+  pub(super) fn add_delete(&mut self, result: Result<()>, path: &str) {
+    match result {
+      Ok(()) => {
+        info!("Deleted '{}'.", path);
+        *self.deleted.get_or_insert(0) += 1;
+      }
+      Err(e) => {
+        error!("Delete '{}' failed: {}", path, e);
+        *self.delete_failures.get_or_insert(0) += 1;
+      }
+    }
+  }
+
+  fn skip(&mut self, path: &str) {
+    info!("Transfer '{}' skipped.", path);
+    self.skipped += 1;
+  }
+
+  /// 输出汇总信息，存在传输或删除失败的文件时返回错误
+  pub(super) fn finish(self) -> Result<()> {
+    if !print_structured(&self)? {
+      let mut line =
+        format!("files: {}, bytes: {}, skipped: {}, failures: {}", self.files, self.bytes, self.skipped, self.failures);
+      if let (Some(deleted), Some(delete_failures)) = (self.deleted, self.delete_failures) {
+        line.push_str(&format!(", deleted: {}, delete failures: {}", deleted, delete_failures));
+      }
+      println!("{}", line);
+    }
+    let delete_failures = self.delete_failures.unwrap_or_default();
+    match (self.failures, delete_failures) {
+      (0, 0) => Ok(()),
+      (n, 0) => bail!("{} files failed to transfer", n),
+      (0, m) => bail!("{} files failed to delete", m),
+      (n, m) => bail!("{} files failed to transfer, {} files failed to delete", n, m),
+    }
+  }
+}
+
+/// 递归上传本地目录到对象存储指定前缀下
+pub(crate) async fn put_dir_to_prefix(
+  client: &StorageClient,
+  root: &Path,
+  prefix: &str,
+  jobs: usize,
+  upload: &UploadOptions,
+) -> Result<DirTransferResult> {
+  let files = walk_dir(root).await?;
+  let total_bytes = files.iter().map(|(_, md)| md.len()).sum();
+  let progress = client.files_progress(files.len(), total_bytes);
+
+  let mut uploads = futures::stream::iter(files)
+    .map(|(path, _)| {
+      let progress = &progress;
+      async move {
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        let object_key = join_object_key(prefix, relative);
+        let result = put_src_to_object_key(client, &path.to_string_lossy(), &object_key, upload, progress).await;
+        progress.file_done();
+        let result = result.map_err(|e| StorageError::from(e).with_path(&object_key, &path));
+        (object_key, result)
+      }
+    })
+    .buffer_unordered(jobs.max(1));
+
+  let mut result = DirTransferResult::default();
+  while let Some((object_key, r)) = uploads.next().await {
+    match r {
+      Ok(r) => result.transferred.push(r),
+      Err(e) => result.failed.push((object_key, e)),
+    }
+  }
+  progress.finish();
+  Ok(result)
+}
+
+/// 递归遍历本地目录，返回所有普通文件路径及其元数据。不跟随符号链接，遇到时输出警告并跳过，避免指向目录外、
+/// 悬空或循环的链接导致重复上传或中断
+pub(super) async fn walk_dir(root: &Path) -> Result<Vec<(PathBuf, std::fs::Metadata)>> {
+  let md = tokio::fs::metadata(root).await.with_context(|| format!("Read metadata of '{}' failed", root.display()))?;
+  if !md.is_dir() {
+    bail!("'{}' is not a directory", root.display());
+  }
+
+  let mut files = Vec::new();
+  let mut dirs = vec![root.to_path_buf()];
+  while let Some(dir) = dirs.pop() {
+    let read_failed = || format!("Read directory '{}' failed", dir.display());
+    let mut rd = tokio::fs::read_dir(&dir).await.with_context(read_failed)?;
+    while let Some(entry) = rd.next_entry().await.with_context(read_failed)? {
+      let path = entry.path();
+      // DirEntry::metadata 不跟随符号链接
+      let md = entry.metadata().await.with_context(|| format!("Read metadata of '{}' failed", path.display()))?;
+      if md.is_symlink() {
+        warn!("Skip the symbolic link '{}'.", path.display());
+      } else if md.is_dir() {
+        dirs.push(path);
+      } else if md.is_file() {
+        files.push((path, md));
+      }
+    }
+  }
+  files.sort_by(|a, b| a.0.cmp(&b.0));
+  Ok(files)
+}
+
+/// 将本地相对路径映射为 prefix 下的对象 key
+pub(super) fn join_object_key(prefix: &str, relative: &Path) -> String {
+  let relative = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+  let prefix = prefix.trim_end_matches('/');
+  if prefix.is_empty() { relative } else { format!("{}/{}", prefix, relative) }
+}
+
+/// 上传本地文件（src 为 `-` 时为标准输入）到对象存储，内容大于 `chunk_size` 时分段并发上传。存储支持时不大于
+/// `chunk_size` 的本地文件一次请求上传并带 Content-MD5 请求头，由存储校验传输内容；指定了自定义元数据的大文件
+/// 通过 [`MultipartUploader`] 分段上传
+pub(crate) async fn put_src_to_object_key(
+  client: &StorageClient,
+  src: &str,
+  object_key: &str,
+  upload: &UploadOptions,
+  progress: &Progress,
+) -> Result<TransferResult> {
+  let op = client.operator();
+  if let Some(mp) = client.multipart()
+    && src != STDIO_PATH
+  {
+    if tokio::fs::metadata(src).await?.len() <= upload.chunk_size.max(1) as u64 {
+      let body = tokio::fs::read(src).await?;
+      let mut hasher = Hasher::default();
+      hasher.update(&body);
+      let uploaded = body.len() as u64;
+      mp.put_object(object_key, &upload.headers_of(src, mp), bytes::Bytes::from(body)).await?;
+      progress.inc(uploaded);
+      info!("Total file upload of {} bytes.", uploaded);
+      let checksums = hasher.finish();
+      verify_object(client, object_key, &checksums).await?;
+      return Ok(transfer_result(object_key, uploaded, checksums));
+    }
+    if !upload.meta.is_empty() {
+      let (uploaded, _) = multipart_upload(mp, src, object_key, upload, None, progress).await?;
+      info!("Total file upload of {} bytes.", uploaded);
+      let checksums = file_checksums(Path::new(src)).await?;
+      verify_object(client, object_key, &checksums).await?;
+      return Ok(transfer_result(object_key, uploaded, checksums));
+    }
+  }
+
+  let mut f: Box<dyn AsyncRead + Unpin + Send> =
+    if src == STDIO_PATH { Box::new(tokio::io::stdin()) } else { Box::new(File::open(src).await?) };
+  let fw = op.writer_with(object_key).chunk(upload.chunk_size.max(1)).concurrent(upload.concurrency.max(1));
+  let mut writer = upload.apply(op, src, fw).await?;
+  let mut buf = vec![0_u8; READ_BUF_SIZE];
   let mut uploaded = 0;
+  let mut hasher = Hasher::default();
 
   loop {
     let n = f.read(&mut buf[..]).await?;
     if n == 0 {
       break;
     }
-    writer.write_all(&buf[..n]).await?;
-    uploaded += n;
+    hasher.update(&buf[..n]);
+    writer.write(bytes::Bytes::copy_from_slice(&buf[..n])).await?;
+    uploaded += n as u64;
+    progress.inc(n as u64);
   }
   writer.close().await?;
 
   info!("Total file upload of {} bytes.", uploaded);
-  Ok(())
+  let checksums = hasher.finish();
+  verify_object(client, object_key, &checksums).await?;
+  Ok(transfer_result(object_key, uploaded, checksums))
+}
+
+/// 递归下载对象存储指定前缀下的所有对象到本地目录
+pub(crate) async fn get_prefix_to_dir(
+  client: &StorageClient,
+  prefix: &str,
+  root: &Path,
+  jobs: usize,
+  policy: ExistingPolicy,
+) -> Result<DirTransferResult> {
+  let prefix = match prefix.trim_end_matches('/') {
+    "" => "/".to_string(),
+    p => format!("{}/", p),
+  };
+  let objects = client
+    .list(&prefix, true, None)
+    .await?
+    .into_iter()
+    .filter(|meta| meta.mode == "file")
+    .map(|meta| (meta.path, meta.content_length))
+    .collect::<Vec<_>>();
+  let progress = client.files_progress(objects.len(), objects.iter().map(|(_, size)| size).sum());
+
+  let mut downloads = futures::stream::iter(objects)
+    .map(|(object_key, size)| {
+      let prefix = &prefix;
+      let progress = &progress;
+      async move {
+        let result = match local_path_of(root, object_key.strip_prefix(prefix.as_str()).unwrap_or(&object_key)) {
+          Some(path) => get_object_key_to_path(client, &object_key, &path, policy, progress)
+            .await
+            .map_err(|e| StorageError::from(e).with_path(&object_key, &path)),
+          None => Err(StorageError::Other(anyhow!("The object key can not be mapped to a local path"))),
+        };
+        if matches!(result, Ok(None)) {
+          progress.inc(size);
+        }
+        progress.file_done();
+        (object_key, result)
+      }
+    })
+    .buffer_unordered(jobs.max(1));
+
+  let mut result = DirTransferResult::default();
+  while let Some((object_key, r)) = downloads.next().await {
+    match r {
+      Ok(Some(r)) => result.transferred.push(r),
+      Ok(None) => result.skipped.push(object_key),
+      Err(e) => result.failed.push((object_key, e)),
+    }
+  }
+  progress.finish();
+  Ok(result)
+}
+
+/// 将对象相对 key 映射为 root 目录下的本地路径，拒绝包含 `..` 等越出 root 的 key
+pub(super) fn local_path_of(root: &Path, relative_key: &str) -> Option<PathBuf> {
+  let mut path = root.to_path_buf();
+  for part in relative_key.split('/').filter(|p| !p.is_empty() && *p != ".") {
+    if part == ".." {
+      return None;
+    }
+    path.push(part);
+  }
+  (path != root).then_some(path)
 }
 
-/// 下载对象存储文件到本地
-async fn get_object_key_to_dst(op: &Operator, object_key: &str, dst: &str) -> Result<()> {
+/// 下载对象存储文件到本地路径，按需创建父目录
+pub(super) async fn get_object_key_to_path(
+  client: &StorageClient,
+  object_key: &str,
+  path: &Path,
+  policy: ExistingPolicy,
+  progress: &Progress,
+) -> Result<Option<TransferResult>> {
+  if let Some(parent) = path.parent() {
+    tokio::fs::create_dir_all(parent).await?;
+  }
+  get_object_key_to_dst(client, object_key, &path.to_string_lossy(), policy, None, progress).await
+}
+
+/// 下载对象存储文件到本地，当本地文件已存在且策略为跳过时返回 `None`。range 为 `None` 时下载整个对象并校验完整性，
+/// 否则仅下载指定的字节范围。
+///
+/// 先下载到同目录下的临时文件，完成且校验通过后再重命名为 dst，失败时删除临时文件，不会留下损坏的文件或破坏已存在
+/// 的文件。dst 已存在且不是普通文件（如 `/dev/null`）时直接写入
+pub(crate) async fn get_object_key_to_dst(
+  client: &StorageClient,
+  object_key: &str,
+  dst: &str,
+  policy: ExistingPolicy,
+  range: Option<Range<u64>>,
+  progress: &Progress,
+) -> Result<Option<TransferResult>> {
+  let existing = tokio::fs::metadata(dst).await.ok();
+  match (policy, &existing) {
+    (ExistingPolicy::Skip, Some(_)) => return Ok(None),
+    (ExistingPolicy::Fail, Some(_)) => {
+      let err = std::io::Error::new(std::io::ErrorKind::AlreadyExists, format!("The file '{}' already exists", dst));
+      return Err(err.into());
+    }
+    _ => {}
+  }
+  let tmp = match existing {
+    Some(md) if !md.is_file() => None,
+    _ => Some(format!("{}.{}.tmp", dst, std::process::id())),
+  };
+
+  let result = download_to(client, object_key, tmp.as_deref().unwrap_or(dst), range, progress).await;
+  if let Some(tmp) = tmp.as_deref() {
+    match &result {
+      Ok(_) => tokio::fs::rename(tmp, dst).await?,
+      Err(_) => {
+        tokio::fs::remove_file(tmp).await.ok();
+      }
+    }
+  }
+  result.map(Some)
+}
+
+/// 下载对象（或指定的字节范围）写入 path，下载整个对象时校验完整性
+async fn download_to(
+  client: &StorageClient,
+  object_key: &str,
+  path: &str,
+  range: Option<Range<u64>>,
+  progress: &Progress,
+) -> Result<TransferResult> {
   use tokio::io::AsyncWriteExt;
 
-  let mut f = File::create_new(dst).await?;
-  let reader = op.reader_with(object_key).await?;
+  let mut f = File::create(path).await?;
+  let reader = client.operator().reader_with(object_key).await?;
   let mut readed = 0u64;
+  let mut hasher = Hasher::default();
+  let verify = range.is_none();
 
-  let mut bs = reader.into_bytes_stream(..).await?;
-  while let Ok(Some(item)) = bs.try_next().await {
+  let mut bs = match range {
+    Some(range) => reader.into_bytes_stream(range).await?,
+    None => reader.into_bytes_stream(..).await?,
+  };
+  while let Some(item) = bs.try_next().await? {
     if item.is_empty() {
       break;
     }
     readed += item.len() as u64;
+    hasher.update(&item);
     f.write_all(&item).await?;
+    progress.inc(item.len() as u64);
   }
 
   info!("Total file download of {} bytes.", readed);
   f.flush().await?;
+  drop(f);
+  let checksums = hasher.finish();
+  if verify {
+    verify_object(client, object_key, &checksums).await?;
+  }
+  Ok(transfer_result(object_key, readed, checksums))
+}
+
+/// 解析 `<name>:<key>` 形式的存储位置，`name` 未在配置中定义时输出警告并视为默认存储下的 key，避免拼错的存储名
+/// 被静默当作 key 的一部分
+fn parse_storage_location<'a>(conf: &DevopsConf, location: &'a str) -> (Option<&'a str>, &'a str) {
+  match location.split_once(':') {
+    Some((name, key)) if conf.named_storage(name).is_some() => (Some(name), key),
+    Some((name, _)) => {
+      warn!("'{}' is not a storage in [storages], '{}' is treated as an object key of [storage].", name, location);
+      (None, location)
+    }
+    None => (None, location),
+  }
+}
+
+/// 复制对象，源与目标位于不同的存储时在两个 Operator 之间流式复制，不经过本地磁盘
+async fn copy_between_storages(conf: &DevopsConf, src: &str, dst: &str) -> Result<()> {
+  let (src_name, src_key) = parse_storage_location(conf, src);
+  let (dst_name, dst_key) = parse_storage_location(conf, dst);
+  let src_op = match src_name {
+    Some(name) => get_named_operator(conf, name).await?,
+    None => get_operator(conf).await?,
+  };
+  if src_name == dst_name {
+    return copy_object_key(&src_op, src_key, dst_key).await;
+  }
+
+  let dst_op = match dst_name {
+    Some(name) => get_named_operator(conf, name).await?,
+    None => get_operator(conf).await?,
+  };
+  let n = stream_copy(&src_op, src_key, &dst_op, dst_key).await?;
+  println!("Copied '{}' to '{}' by streaming {} bytes between storages.", src, dst, n);
   Ok(())
 }
 
+/// 复制对象存储文件，优先使用服务端复制
+async fn copy_object_key(op: &Operator, src_key: &str, dst_key: &str) -> Result<()> {
+  if op.info().full_capability().copy {
+    op.copy(src_key, dst_key).await?;
+    println!("Copied '{}' to '{}' by server-side copy.", src_key, dst_key);
+  } else {
+    let n = stream_copy(op, src_key, op, dst_key).await?;
+    println!("Copied '{}' to '{}' by streaming {} bytes through the client.", src_key, dst_key, n);
+  }
+  Ok(())
+}
+
+/// 移动对象存储文件，优先使用服务端重命名，其次服务端复制后删除，最后流式复制后删除
+async fn move_object_key(op: &Operator, src_key: &str, dst_key: &str) -> Result<()> {
+  let cap = op.info().full_capability();
+  if cap.rename {
+    op.rename(src_key, dst_key).await?;
+    println!("Moved '{}' to '{}' by server-side rename.", src_key, dst_key);
+    return Ok(());
+  }
+
+  if cap.copy {
+    op.copy(src_key, dst_key).await?;
+    op.delete(src_key).await?;
+    println!("Moved '{}' to '{}' by server-side copy and delete.", src_key, dst_key);
+  } else {
+    let n = stream_copy(op, src_key, op, dst_key).await?;
+    op.delete(src_key).await?;
+    println!("Moved '{}' to '{}' by streaming {} bytes through the client and delete.", src_key, dst_key, n);
+  }
+  Ok(())
+}
+
+/// 从源对象流式读取并写入目标对象，不经过本地磁盘，返回复制的字节数
+pub(super) async fn stream_copy(
+  src_op: &Operator,
+  src_key: &str,
+  dst_op: &Operator,
+  dst_key: &str,
+) -> Result<u64> {
+  let reader = src_op.reader_with(src_key).await?;
+  let mut bs = reader.into_bytes_stream(..).await?;
+  let mut writer = dst_op.writer_with(dst_key).await?;
+  let mut copied = 0u64;
+
+  while let Some(item) = bs.try_next().await? {
+    copied += item.len() as u64;
+    writer.write(item).await?;
+  }
+  writer.close().await?;
+  Ok(copied)
+}
+
+/// 删除对象存储文件，递归时批量删除前缀下的所有对象
+/// 删除前确认，指定 --yes 时直接通过。标准输入不是终端（如管道、CI）时读取到 EOF 会被当作取消，需通过 --yes 明确确认
+pub(super) fn confirm_delete(yes: bool, count: usize, target: &str) -> Result<bool> {
+  if yes {
+    return Ok(true);
+  }
+  if !std::io::stdin().is_terminal() {
+    bail!("Cannot confirm deleting {} objects under '{}': stdin is not a terminal, use --yes", count, target);
+  }
+  Ok(confirm(&format!("Delete {} objects under '{}'?", count, target))?)
+}
+
+async fn delete_object_key(
+  client: &StorageClient,
+  object_key: &str,
+  recursive: bool,
+  yes: bool,
+  dry_run: bool,
+) -> Result<()> {
+  let object_keys = if recursive {
+    let prefix = match object_key.trim_end_matches('/') {
+      "" => bail!("Refusing to recursively delete the whole bucket"),
+      p => format!("{}/", p),
+    };
+    let entries = client.list(&prefix, true, None).await?;
+    entries.into_iter().filter(|meta| meta.mode == "file").map(|meta| meta.path).collect::<Vec<_>>()
+  } else {
+    match client.stat(object_key).await {
+      Err(StorageError::NotFound(_)) => bail!("The object '{}' does not exist", object_key),
+      r => r?,
+    };
+    vec![object_key.to_string()]
+  };
+
+  if dry_run {
+    if !print_structured(&object_keys)? {
+      for key in object_keys.iter() {
+        println!("delete: {}", key);
+      }
+    }
+    info!("Dry run: {} objects to delete.", object_keys.len());
+    return Ok(());
+  }
+  if object_keys.is_empty() {
+    info!("No objects found under '{}'.", object_key);
+    return Ok(());
+  }
+  if !confirm_delete(yes, object_keys.len(), object_key)? {
+    info!("Delete cancelled.");
+    return Ok(());
+  }
+
+  let deleted = object_keys.len();
+  if recursive {
+    client.delete_all(object_keys).await?;
+  } else {
+    client.delete(object_key).await?;
+  }
+  info!("Total {} objects deleted.", deleted);
+  Ok(())
+}
+
+pub(super) fn transfer_result(object_key: &str, bytes: u64, checksums: Checksums) -> TransferResult {
+  TransferResult {
+    object_key: object_key.to_string(),
+    bytes,
+    md5: checksums.md5,
+    sha256: checksums.sha256,
+    crc64: checksums.crc64,
+  }
+}
+
 /// 输出对象存储文件元信息
-async fn dump_stat(op: &Operator, object_key: &str) -> Result<()> {
-  let md = op.stat(object_key).await?;
+async fn dump_stat(client: &StorageClient, object_key: &str) -> Result<()> {
+  let meta = client.stat(object_key).await?;
+  if print_structured(&meta)? {
+    return Ok(());
+  }
   println!(
     r#"metakey: {:?}
 mode: {}
@@ -83,17 +945,108 @@ content_type: {}
 etag: {}
 last_modified: {}
 version: {}"#,
-    md.metakey().into_iter().map(|k| format!("{:?}", k)).collect::<Vec<_>>(),
-    md.mode(),
-    md.cache_control().unwrap_or_default(),
-    md.content_disposition().unwrap_or_default(),
-    md.content_length(),
-    md.content_md5().unwrap_or_default(),
-    md.content_range().as_ref().map(|v| v.to_string()).unwrap_or_default(),
-    md.content_type().unwrap_or_default(),
-    md.etag().unwrap_or_default(),
-    md.last_modified().as_ref().map(|d| d.to_rfc3339()).unwrap_or_default(),
-    md.version().unwrap_or_default(),
+    meta.metakey,
+    meta.mode,
+    meta.cache_control.unwrap_or_default(),
+    meta.content_disposition.unwrap_or_default(),
+    meta.content_length,
+    meta.content_md5.unwrap_or_default(),
+    meta.content_range.unwrap_or_default(),
+    meta.content_type.unwrap_or_default(),
+    meta.etag.unwrap_or_default(),
+    meta.last_modified.unwrap_or_default(),
+    meta.version.unwrap_or_default(),
   );
   Ok(())
 }
+
+/// 列出对象存储指定前缀下的对象，每行输出：key、size、last_modified、etag。table 格式时逐行输出，不缓存全部结果
+async fn list_prefix(client: &StorageClient, prefix: &str, recursive: bool, limit: Option<usize>) -> Result<()> {
+  let mut entries = client.list_stream(prefix, recursive, limit).await?;
+  if is_structured() {
+    let entries = entries.try_collect::<Vec<_>>().await?;
+    print_structured(&entries)?;
+    info!("Total {} entries listed.", entries.len());
+    return Ok(());
+  }
+
+  let mut listed = 0;
+  while let Some(meta) = entries.try_next().await? {
+    println!(
+      "{}\t{}\t{}\t{}",
+      meta.path,
+      if meta.mode == "dir" { "-".to_string() } else { meta.content_length.to_string() },
+      meta.last_modified.unwrap_or_default(),
+      meta.etag.unwrap_or_default(),
+    );
+    listed += 1;
+  }
+
+  info!("Total {} entries listed.", listed);
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_join_object_key() {
+    let relative = Path::new("bin").join("devops-cli");
+    assert_eq!(join_object_key("releases/v1.2/", &relative), "releases/v1.2/bin/devops-cli");
+    assert_eq!(join_object_key("releases/v1.2", &relative), "releases/v1.2/bin/devops-cli");
+    assert_eq!(join_object_key("", &relative), "bin/devops-cli");
+  }
+
+  #[test]
+  fn test_content_type_of() {
+    let upload = UploadOptions::default();
+    assert_eq!(upload.content_type_of("dist/index.html").as_deref(), Some("text/html"));
+    assert_eq!(upload.content_type_of("dist/app.js").as_deref(), Some("text/javascript"));
+    assert_eq!(upload.content_type_of("dist/devops-cli"), None);
+    assert_eq!(upload.content_type_of("-"), None);
+
+    let upload = UploadOptions { content_type: Some("application/gzip".into()), ..Default::default() };
+    assert_eq!(upload.content_type_of("dist/index.html").as_deref(), Some("application/gzip"));
+  }
+
+  #[test]
+  fn test_transfer_summary_delete_failures() {
+    let mut summary = TransferSummary::with_deletes();
+    summary.add(Ok(3), "a.txt");
+    summary.add_delete(Ok(()), "b.txt");
+    summary.add_delete(Err(anyhow!("permission denied")), "c.txt");
+    assert_eq!((summary.deleted, summary.delete_failures), (Some(1), Some(1)));
+    assert_eq!(summary.finish().unwrap_err().to_string(), "1 files failed to delete");
+    assert!(TransferSummary::with_deletes().finish().is_ok());
+  }
+
+  #[cfg(unix)]
+  #[tokio::test]
+  async fn test_walk_dir_skip_symlinks() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("storage-cli-walk-test-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("sub"))?;
+    std::fs::write(dir.join("sub").join("a.txt"), b"a")?;
+    std::os::unix::fs::symlink(dir.join("missing.txt"), dir.join("dangling"))?;
+    std::os::unix::fs::symlink(dir.join("loop"), dir.join("loop"))?;
+    std::os::unix::fs::symlink(&dir, dir.join("sub").join("parent"))?;
+
+    let files = walk_dir(&dir).await?;
+    assert_eq!(files.iter().map(|(p, _)| p.clone()).collect::<Vec<_>>(), [dir.join("sub").join("a.txt")]);
+    assert_eq!(files[0].1.len(), 1);
+
+    let err = walk_dir(&dir.join("missing")).await.unwrap_err();
+    assert!(err.to_string().contains("missing"), "{}", err);
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+  }
+
+  #[test]
+  fn test_local_path_of() {
+    let root = Path::new("dist");
+    assert_eq!(local_path_of(root, "bin/devops-cli"), Some(root.join("bin").join("devops-cli")));
+    assert_eq!(local_path_of(root, "/bin//devops-cli"), Some(root.join("bin").join("devops-cli")));
+    assert_eq!(local_path_of(root, "../etc/passwd"), None);
+    assert_eq!(local_path_of(root, ""), None);
+  }
+}
@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use log::warn;
+use reqwest::{Client, Request, Response, StatusCode};
+
+use crate::conf::TransferConf;
+
+/// 绕过 opendal 直接发送的请求（如预签名的 HEAD 请求、分段上传）使用的 HTTP 客户端，与 Operator 一样按
+/// `[transfer]` 配置超时与重试。由 [`StorageClient`](crate::client::StorageClient) 持有，克隆时共用内部的连接池
+#[derive(Debug, Clone)]
+pub(crate) struct HttpClient {
+  client: Client,
+  transfer: TransferConf,
+}
+
+impl HttpClient {
+  pub(crate) fn new(tc: &TransferConf) -> Result<Self> {
+    let client = Client::builder()
+      .connect_timeout(Duration::from_secs(tc.timeout))
+      .read_timeout(Duration::from_secs(tc.io_timeout))
+      .build()?;
+    Ok(Self { client, transfer: tc.clone() })
+  }
+
+  /// 替换内部的客户端，用于测试时将域名解析到本地的模拟服务
+  #[cfg(test)]
+  pub(crate) fn with_client(mut self, client: Client) -> Self {
+    self.client = client;
+    self
+  }
+
+  /// 发送请求，网络错误、服务端错误与限流时以指数退避重试。每次发送前调用 build 重新构建请求，以便重新签名。
+  /// 重试后仍失败的响应原样返回，由调用方处理
+  pub(crate) async fn send(&self, build: impl Fn(&Client) -> Result<Request>) -> Result<Response> {
+    let tc = &self.transfer;
+    let mut delay = Duration::from_secs_f64(tc.retry_min_delay);
+    let mut retries = 0;
+    loop {
+      let result = self.client.execute(build(&self.client)?).await;
+      let reason = match result {
+        Ok(resp) if !is_retryable(resp.status()) || retries >= tc.max_retries => return Ok(resp),
+        Ok(resp) => format!("status {}", resp.status()),
+        Err(e) if retries >= tc.max_retries => return Err(e.into()),
+        Err(e) => e.to_string(),
+      };
+      retries += 1;
+      warn!("Request failed, retry after {:.1}s: {}", delay.as_secs_f64(), reason);
+      tokio::time::sleep(delay).await;
+      delay = (delay * 2).min(Duration::from_secs_f64(tc.retry_max_delay));
+    }
+  }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+  status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+  };
+
+  use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+  };
+
+  use super::*;
+
+  /// 模拟服务：前 failures 个请求返回 503，之后返回 200
+  async fn mock_server(failures: usize, count: Arc<AtomicUsize>) -> Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+      while let Ok((mut stream, _)) = listener.accept().await {
+        let mut buf = [0u8; 1024];
+        let _ = stream.read(&mut buf).await;
+        let status = if count.fetch_add(1, Ordering::SeqCst) < failures { "503 Service Unavailable" } else { "200 OK" };
+        let resp = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status);
+        let _ = stream.write_all(resp.as_bytes()).await;
+      }
+    });
+    Ok(format!("http://{}/", addr))
+  }
+
+  #[tokio::test]
+  async fn test_send_retry() -> Result<()> {
+    let tc = TransferConf { max_retries: 2, retry_min_delay: 0.01, retry_max_delay: 0.01, ..Default::default() };
+    let http = HttpClient::new(&tc)?;
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let url = mock_server(2, count.clone()).await?;
+    let resp = http.send(|client| Ok(client.head(&url).build()?)).await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let url = mock_server(3, count.clone()).await?;
+    let resp = http.send(|client| Ok(client.head(&url).build()?)).await?;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+    Ok(())
+  }
+}
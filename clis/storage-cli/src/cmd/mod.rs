@@ -1,5 +1,27 @@
+mod checksum;
+mod config_operation;
 mod devops_cmd;
 mod file_operation;
+mod http;
+mod multipart;
+mod output;
+mod presign;
+mod preview;
+mod progress;
+mod resume;
+mod sync;
 
+pub use config_operation::ConfigOperation;
 pub use devops_cmd::{DevopsCmd, StorageSource};
-pub use file_operation::FileOperation;
+pub use file_operation::{ExistingPolicy, FileOperation, UploadOptions};
+pub(crate) use file_operation::{get_object_key_to_dst, get_prefix_to_dir, put_dir_to_prefix, put_src_to_object_key};
+pub(crate) use http::HttpClient;
+pub(crate) use multipart::MultipartUploader;
+pub use output::{ObjectMeta, OutputFormat, set_output_format};
+pub use presign::PresignOperation;
+pub use preview::ByteRange;
+pub(crate) use preview::resolve_range;
+pub(crate) use progress::Progress;
+pub(crate) use resume::default_journal_dir;
+pub use progress::set_progress_enabled;
+pub use sync::SyncDirection;
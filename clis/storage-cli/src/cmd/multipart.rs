@@ -0,0 +1,371 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::Engine;
+use bytes::Bytes;
+use md5::{Digest, Md5};
+use reqwest::{Method, Response, StatusCode, Url};
+
+use super::{StorageSource, http::HttpClient};
+use crate::{
+  conf::{StorageConf, TransferConf},
+  operators::BandwidthLimiter,
+};
+
+/// 除最后一段外每段的最小字节数，S3 要求不小于 5MiB，OBS、OSS、COS 的限制更小
+const MIN_PART_SIZE: u64 = 5 * 1024 * 1024;
+/// 单个分段上传最多的段数
+const MAX_PARTS: u64 = 10_000;
+
+/// 分段上传请求的签名器及凭证
+enum Signer {
+  Oss(reqsign::AliyunOssSigner, reqsign::AliyunCredential),
+  Obs(reqsign::HuaweicloudObsSigner, reqsign::HuaweicloudObsCredential),
+  #[cfg(feature = "services-s3")]
+  S3(reqsign::AwsV4Signer, reqsign::AwsCredential),
+  #[cfg(feature = "services-cos")]
+  Cos(reqsign::TencentCosSigner, reqsign::TencentCosCredential),
+}
+
+impl Signer {
+  fn sign(&self, req: &mut reqwest::Request) -> Result<()> {
+    match self {
+      Signer::Oss(signer, cred) => signer.sign(req, cred),
+      Signer::Obs(signer, cred) => signer.sign(req, cred),
+      #[cfg(feature = "services-s3")]
+      Signer::S3(signer, cred) => signer.sign(req, cred),
+      #[cfg(feature = "services-cos")]
+      Signer::Cos(signer, cred) => signer.sign(req, cred),
+    }
+  }
+}
+
+/// 存储返回的错误响应
+#[derive(Debug, thiserror::Error)]
+#[error("{status} {code}: {message}")]
+struct ResponseError {
+  status: StatusCode,
+  code: String,
+  message: String,
+}
+
+impl ResponseError {
+  async fn from_response(resp: Response) -> Self {
+    let status = resp.status();
+    let body = resp.text().await.unwrap_or_default();
+    Self::from_body(status, &body)
+  }
+
+  fn from_body(status: StatusCode, body: &str) -> Self {
+    let value = |tag| xml_value(body, tag).unwrap_or_default().to_string();
+    Self { status, code: value("Code"), message: value("Message") }
+  }
+}
+
+/// 通过 S3 兼容的上传接口（OBS、OSS、S3、COS）上传对象，单段上传与分段上传的每一段均带 Content-MD5 请求头，
+/// 由存储校验传输内容。分段上传时由调用方保存 upload id 与已完成的段用于断点续传。
+///
+/// opendal 的写入不支持设置 Content-MD5，分段写入也不暴露 upload id 与已上传的段，中断后无法继续，因此使用
+/// reqsign 直接签名请求。请求按 `[transfer]` 配置重试、超时与限制带宽
+pub(crate) struct MultipartUploader {
+  http: HttpClient,
+  signer: Signer,
+  /// 对象 URL 的前缀，包含 bucket 与 root，如 `https://<bucket>.<endpoint>/<root>/`
+  base_url: String,
+  limiter: Option<BandwidthLimiter>,
+}
+
+impl std::fmt::Debug for MultipartUploader {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("MultipartUploader").field("base_url", &self.base_url).finish_non_exhaustive()
+  }
+}
+
+impl MultipartUploader {
+  /// 存储服务不支持分段上传接口（如 Azure Blob、GCS、fs）时返回 `None`。http 为按 tc 创建的客户端
+  pub(crate) fn new(
+    service: &StorageSource,
+    sc: &StorageConf,
+    http: &HttpClient,
+    tc: &TransferConf,
+  ) -> Result<Option<Self>> {
+    let signer = match service {
+      StorageSource::Oss => {
+        let c = sc.credentials()?;
+        let cred = reqsign::AliyunCredential {
+          access_key_id: c.ak.clone(),
+          access_key_secret: c.sk.clone(),
+          security_token: c.security_token.clone(),
+          expires_in: None,
+        };
+        Signer::Oss(reqsign::AliyunOssSigner::new(&sc.bucket), cred)
+      }
+      StorageSource::Obs => {
+        let c = sc.credentials()?;
+        let cred = reqsign::HuaweicloudObsCredential {
+          access_key_id: c.ak.clone(),
+          secret_access_key: c.sk.clone(),
+          security_token: c.security_token.clone(),
+        };
+        Signer::Obs(reqsign::HuaweicloudObsSigner::new(&sc.bucket), cred)
+      }
+      #[cfg(feature = "services-s3")]
+      StorageSource::S3 => {
+        let c = sc.credentials()?;
+        let cred = reqsign::AwsCredential {
+          access_key_id: c.ak.clone(),
+          secret_access_key: c.sk.clone(),
+          session_token: c.security_token.clone(),
+          expires_in: None,
+        };
+        Signer::S3(reqsign::AwsV4Signer::new("s3", s3_region(sc)), cred)
+      }
+      #[cfg(feature = "services-cos")]
+      StorageSource::Cos => {
+        let c = sc.credentials()?;
+        let cred = reqsign::TencentCosCredential {
+          secret_id: c.ak.clone(),
+          secret_key: c.sk.clone(),
+          security_token: c.security_token.clone(),
+          expires_in: None,
+        };
+        Signer::Cos(reqsign::TencentCosSigner::new(), cred)
+      }
+      #[allow(unreachable_patterns)]
+      _ => return Ok(None),
+    };
+    Ok(Some(Self {
+      http: http.clone(),
+      signer,
+      base_url: base_url(service, sc)?,
+      limiter: tc.bandwidth.filter(|b| *b > 0).map(|b| BandwidthLimiter::new(b as u64)),
+    }))
+  }
+
+  /// 替换 HTTP 客户端，用于测试时将 bucket 域名解析到本地的模拟服务
+  #[cfg(test)]
+  pub(crate) fn with_client(mut self, client: reqwest::Client) -> Self {
+    self.http = self.http.with_client(client);
+    self
+  }
+
+  /// 每段的大小：不小于 chunk_size 与最小段大小，且段数不超过上限
+  pub(crate) fn part_size(&self, size: u64, chunk_size: usize) -> u64 {
+    (chunk_size as u64).max(MIN_PART_SIZE).max(size.div_ceil(MAX_PARTS))
+  }
+
+  /// 自定义元数据请求头的前缀
+  pub(crate) fn meta_prefix(&self) -> &'static str {
+    match self.signer {
+      Signer::Oss(..) => "x-oss-meta-",
+      Signer::Obs(..) => "x-obs-meta-",
+      #[cfg(feature = "services-s3")]
+      Signer::S3(..) => "x-amz-meta-",
+      #[cfg(feature = "services-cos")]
+      Signer::Cos(..) => "x-cos-meta-",
+    }
+  }
+
+  /// 单次请求上传整个对象。headers 为对象的 Content-Type 等元数据
+  pub(crate) async fn put_object(&self, object_key: &str, headers: &[(String, String)], body: Bytes) -> Result<()> {
+    let mut headers = headers.to_vec();
+    headers.push(("content-md5".to_string(), content_md5(&body)));
+    let len = body.len();
+    self.send(Method::PUT, &self.object_url(object_key), &headers, body).await?;
+    if let Some(limiter) = &self.limiter {
+      limiter.consume(len).await;
+    }
+    Ok(())
+  }
+
+  /// 开始分段上传，返回 upload id。headers 为对象的 Content-Type 等元数据
+  pub(crate) async fn initiate(&self, object_key: &str, headers: &[(String, String)]) -> Result<String> {
+    let url = format!("{}?uploads", self.object_url(object_key));
+    let body = self.send(Method::POST, &url, headers, Bytes::new()).await?.text().await?;
+    xml_value(&body, "UploadId")
+      .map(ToString::to_string)
+      .ok_or_else(|| anyhow!("No upload id is returned for '{}': {}", object_key, body))
+  }
+
+  /// 上传一段，part_number 从 1 开始，返回该段的 etag
+  pub(crate) async fn upload_part(
+    &self,
+    object_key: &str,
+    upload_id: &str,
+    part_number: u32,
+    body: Bytes,
+  ) -> Result<String> {
+    let url = format!(
+      "{}?partNumber={}&uploadId={}",
+      self.object_url(object_key),
+      part_number,
+      percent_encode(upload_id, false)
+    );
+    let len = body.len();
+    let resp = self.send(Method::PUT, &url, &[("content-md5".to_string(), content_md5(&body))], body).await?;
+    if let Some(limiter) = &self.limiter {
+      limiter.consume(len).await;
+    }
+    let etag = resp.headers().get(reqwest::header::ETAG).and_then(|v| v.to_str().ok());
+    etag
+      .map(ToString::to_string)
+      .ok_or_else(|| anyhow!("No etag is returned for part {} of '{}'", part_number, object_key))
+  }
+
+  /// 合并已上传的段，parts 为段号与 etag
+  pub(crate) async fn complete(&self, object_key: &str, upload_id: &str, parts: &BTreeMap<u32, String>) -> Result<()> {
+    let url = format!("{}?uploadId={}", self.object_url(object_key), percent_encode(upload_id, false));
+    let mut xml = String::from("<CompleteMultipartUpload>");
+    for (number, etag) in parts {
+      xml.push_str(&format!("<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>", number, etag));
+    }
+    xml.push_str("</CompleteMultipartUpload>");
+    let headers = [("content-type".to_string(), "application/xml".to_string())];
+    let resp = self.send(Method::POST, &url, &headers, Bytes::from(xml)).await?;
+    // S3 合并失败时可能返回 200 与错误信息
+    let status = resp.status();
+    let body = resp.text().await?;
+    if body.contains("<Error>") {
+      return Err(ResponseError::from_body(status, &body).into());
+    }
+    Ok(())
+  }
+
+  /// 取消分段上传，删除已上传的段
+  pub(crate) async fn abort(&self, object_key: &str, upload_id: &str) -> Result<()> {
+    let url = format!("{}?uploadId={}", self.object_url(object_key), percent_encode(upload_id, false));
+    self.send(Method::DELETE, &url, &[], Bytes::new()).await?;
+    Ok(())
+  }
+
+  /// 分段上传已不存在，如已合并、已取消或过期被清理
+  pub(crate) fn is_no_such_upload(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<ResponseError>().is_some_and(|e| e.code == "NoSuchUpload")
+  }
+
+  fn object_url(&self, object_key: &str) -> String {
+    format!("{}{}", self.base_url, percent_encode(object_key.trim_start_matches('/'), true))
+  }
+
+  /// 签名并发送请求，服务端错误、限流与网络错误按 `[transfer]` 配置以指数退避重试
+  async fn send(&self, method: Method, url: &str, headers: &[(String, String)], body: Bytes) -> Result<Response> {
+    let resp = self
+      .http
+      .send(|client| {
+        let mut req = client.request(method.clone(), url).body(body.clone());
+        for (name, value) in headers {
+          req = req.header(name, value);
+        }
+        let mut req = req.build()?;
+        self.signer.sign(&mut req)?;
+        Ok(req)
+      })
+      .await
+      .with_context(|| format!("{} {} failed", method, url))?;
+    if resp.status().is_success() {
+      return Ok(resp);
+    }
+    let err = ResponseError::from_response(resp).await;
+    Err(anyhow::Error::from(err).context(format!("{} {} failed", method, url)))
+  }
+}
+
+#[cfg(feature = "services-s3")]
+fn s3_region(sc: &StorageConf) -> &str {
+  sc.region.as_deref().unwrap_or("us-east-1")
+}
+
+/// 对象 URL 的前缀：OBS、OSS、COS 与 virtual host style 的 S3 为 `<bucket>.<endpoint>`，path style 的 S3 为
+/// `<endpoint>/<bucket>`，与 opendal 访问的地址一致
+fn base_url(service: &StorageSource, sc: &StorageConf) -> Result<String> {
+  let (endpoint, path_style) = match service {
+    #[cfg(feature = "services-s3")]
+    StorageSource::S3 if sc.endpoint.is_empty() => (format!("s3.{}.amazonaws.com", s3_region(sc)), false),
+    #[cfg(feature = "services-s3")]
+    StorageSource::S3 => (sc.endpoint.clone(), !sc.virtual_host_style),
+    _ => (sc.endpoint.clone(), false),
+  };
+  let endpoint = if endpoint.contains("://") { endpoint } else { format!("https://{}", endpoint) };
+  let url = Url::parse(&endpoint).with_context(|| format!("Invalid endpoint '{}'", endpoint))?;
+  let host = url.host_str().ok_or_else(|| anyhow!("Invalid endpoint '{}'", endpoint))?;
+  let port = url.port().map(|p| format!(":{}", p)).unwrap_or_default();
+  if sc.bucket.is_empty() {
+    bail!("The bucket is not set");
+  }
+
+  let mut base = if path_style {
+    format!("{}://{}{}/{}/", url.scheme(), host, port, sc.bucket)
+  } else if host.starts_with(&format!("{}.", sc.bucket)) {
+    format!("{}://{}{}/", url.scheme(), host, port)
+  } else {
+    format!("{}://{}.{}{}/", url.scheme(), sc.bucket, host, port)
+  };
+  let root = sc.root.as_deref().unwrap_or_default().trim_matches('/');
+  if !root.is_empty() {
+    base.push_str(&percent_encode(root, true));
+    base.push('/');
+  }
+  Ok(base)
+}
+
+/// Content-MD5 请求头的值：内容 MD5 的 base64 编码
+fn content_md5(body: &[u8]) -> String {
+  base64::engine::general_purpose::STANDARD.encode(Md5::digest(body))
+}
+
+/// URL 编码，保留 RFC 3986 的非保留字符，keep_slash 时保留路径分隔符 `/`
+fn percent_encode(s: &str, keep_slash: bool) -> String {
+  let mut encoded = String::with_capacity(s.len());
+  for b in s.bytes() {
+    match b {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(b as char),
+      b'/' if keep_slash => encoded.push('/'),
+      b => encoded.push_str(&format!("%{:02X}", b)),
+    }
+  }
+  encoded
+}
+
+/// 读取 XML 中第一个 `<tag>` 元素的文本
+fn xml_value<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+  let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+  let end = xml[start..].find(&format!("</{}>", tag))?;
+  Some(&xml[start..start + end])
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_base_url() -> Result<()> {
+    let mut sc = StorageConf::default();
+    sc.endpoint = "obs.cn-southwest-2.myhuaweicloud.com".into();
+    sc.bucket = "demo".into();
+    sc.root = Some("/backup/".into());
+    assert_eq!(base_url(&StorageSource::Obs, &sc)?, "https://demo.obs.cn-southwest-2.myhuaweicloud.com/backup/");
+    sc.endpoint = "http://demo.oss.test:9000".into();
+    sc.root = None;
+    assert_eq!(base_url(&StorageSource::Oss, &sc)?, "http://demo.oss.test:9000/");
+    Ok(())
+  }
+
+  #[test]
+  fn test_content_md5() {
+    assert_eq!(content_md5(b""), "1B2M2Y8AsgTpgAmY7PhCfg==");
+    assert_eq!(content_md5(b"hello"), "XUFAKrxLKna5cZ2REBfFkg==");
+  }
+
+  #[test]
+  fn test_percent_encode() {
+    assert_eq!(percent_encode("a b/中.txt", true), "a%20b/%E4%B8%AD.txt");
+    assert_eq!(percent_encode("a/b+c", false), "a%2Fb%2Bc");
+  }
+
+  #[test]
+  fn test_xml_value() {
+    let xml = "<InitiateMultipartUploadResult><Bucket>demo</Bucket><UploadId>0004B9</UploadId></Initiate...>";
+    assert_eq!(xml_value(xml, "UploadId"), Some("0004B9"));
+    assert_eq!(xml_value(xml, "Key"), None);
+  }
+}
@@ -0,0 +1,106 @@
+use std::sync::RwLock;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use opendal::{Metadata, Metakey};
+use serde::Serialize;
+
+/// 命令结果的输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+  /// 便于阅读的文本，列表为制表符分隔的表格
+  #[default]
+  Table,
+  Json,
+  Yaml,
+}
+
+static OUTPUT_FORMAT: RwLock<OutputFormat> = RwLock::new(OutputFormat::Table);
+
+/// 设置 stat、list、sync 等命令结果的输出格式
+pub fn set_output_format(format: OutputFormat) {
+  *OUTPUT_FORMAT.write().unwrap_or_else(|e| e.into_inner()) = format;
+}
+
+fn output_format() -> OutputFormat {
+  *OUTPUT_FORMAT.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 是否以 JSON 或 YAML 输出。结构化输出需要完整的结果，table 格式可逐行输出
+pub(super) fn is_structured() -> bool {
+  output_format() != OutputFormat::Table
+}
+
+/// 以 JSON 或 YAML 输出 value 并返回 `true`；table 格式时不输出并返回 `false`，由调用方输出文本
+pub(super) fn print_structured<T: Serialize>(value: &T) -> Result<bool> {
+  match output_format() {
+    OutputFormat::Table => return Ok(false),
+    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+    OutputFormat::Yaml => print!("{}", serde_yaml::to_string(value)?),
+  }
+  Ok(true)
+}
+
+/// 对象元信息，字段名保持稳定供脚本解析，last_modified 为 RFC 3339 格式。列表时未获取的字段为空
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ObjectMeta {
+  pub path: String,
+  /// `file`、`dir` 或 `unknown`
+  pub mode: String,
+  pub content_length: u64,
+  /// 范围读取时返回的字节范围，如 `bytes 0-99/1000`
+  pub content_range: Option<String>,
+  pub content_type: Option<String>,
+  pub content_md5: Option<String>,
+  pub content_disposition: Option<String>,
+  pub cache_control: Option<String>,
+  pub etag: Option<String>,
+  pub last_modified: Option<String>,
+  pub version: Option<String>,
+  /// 已获取的元信息字段，如 `Mode`、`ContentLength`，`Complete` 表示已获取全部字段
+  pub metakey: Vec<String>,
+}
+
+impl ObjectMeta {
+  pub fn new(path: &str, md: &Metadata) -> Self {
+    // 仅读取已获取的字段，opendal 读取未获取的字段时会断言失败
+    let keys = md.metakey();
+    let has = |key: Metakey| keys.contains(key) || keys.contains(Metakey::Complete);
+    Self {
+      path: path.to_string(),
+      mode: if has(Metakey::Mode) { md.mode().to_string() } else { "unknown".to_string() },
+      content_length: if has(Metakey::ContentLength) { md.content_length() } else { 0 },
+      content_range: has(Metakey::ContentRange).then(|| md.content_range()).flatten().map(|r| r.to_string()),
+      content_type: has(Metakey::ContentType).then(|| md.content_type()).flatten().map(ToString::to_string),
+      content_md5: has(Metakey::ContentMd5).then(|| md.content_md5()).flatten().map(ToString::to_string),
+      content_disposition: has(Metakey::ContentDisposition)
+        .then(|| md.content_disposition())
+        .flatten()
+        .map(ToString::to_string),
+      cache_control: has(Metakey::CacheControl).then(|| md.cache_control()).flatten().map(ToString::to_string),
+      etag: has(Metakey::Etag).then(|| md.etag()).flatten().map(ToString::to_string),
+      last_modified: has(Metakey::LastModified).then(|| md.last_modified()).flatten().map(|d| d.to_rfc3339()),
+      version: has(Metakey::Version).then(|| md.version()).flatten().map(ToString::to_string),
+      metakey: keys.into_iter().map(|k| format!("{:?}", k)).collect(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_object_meta() -> Result<()> {
+    let md = Metadata::new(opendal::EntryMode::FILE).with_content_length(16).with_etag("\"abc\"".into());
+    let meta = ObjectMeta::new("software/demo.txt", &md);
+    let value = serde_json::to_value(&meta)?;
+    assert_eq!(value["path"], "software/demo.txt");
+    assert_eq!(value["mode"], "file");
+    assert_eq!(value["content_length"], 16);
+    assert_eq!(value["etag"], "\"abc\"");
+    assert!(value["last_modified"].is_null());
+    assert_eq!(value["metakey"], serde_json::json!(["Mode", "ContentLength", "Etag"]));
+    Ok(())
+  }
+}
@@ -0,0 +1,101 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use anyhow::{Result, bail};
+use clap::Subcommand;
+use opendal::{Operator, raw::PresignedRequest};
+use serde::Serialize;
+
+use super::output::print_structured;
+use crate::utils::parse_duration;
+
+#[derive(Debug, Subcommand)]
+pub enum PresignOperation {
+  /// 生成下载对象的预签名 URL
+  Get {
+    object_key: String,
+    /// 有效期，如 `30s`、`15m`、`1h`、`7d`
+    #[arg(short, long, default_value = "1h", value_parser = parse_duration)]
+    expires: Duration,
+  },
+  /// 生成上传对象的预签名 URL，使用 PUT 方法上传
+  Put {
+    object_key: String,
+    /// 有效期，如 `30s`、`15m`、`1h`、`7d`
+    #[arg(short, long, default_value = "1h", value_parser = parse_duration)]
+    expires: Duration,
+    /// 签名中包含的 Content-Type，上传时须携带相同的请求头
+    #[arg(long)]
+    content_type: Option<String>,
+  },
+}
+
+impl PresignOperation {
+  pub async fn execute(&self, op: &Operator) -> Result<()> {
+    let cap = op.info().full_capability();
+    let (req, expires) = match self {
+      PresignOperation::Get { object_key, expires } => {
+        if !cap.presign_read {
+          bail!("The storage '{}' does not support presigned download url", op.info().scheme());
+        }
+        (op.presign_read(object_key, *expires).await?, *expires)
+      }
+      PresignOperation::Put { object_key, expires, content_type } => {
+        if !cap.presign_write {
+          bail!("The storage '{}' does not support presigned upload url", op.info().scheme());
+        }
+        let mut fut = op.presign_write_with(object_key, *expires);
+        if let Some(content_type) = content_type.as_deref() {
+          fut = fut.content_type(content_type);
+        }
+        (fut.await?, *expires)
+      }
+    };
+
+    let url = PresignedUrl::new(&req, expires)?;
+    if !print_structured(&url)? {
+      println!("{}", url.url);
+    }
+    Ok(())
+  }
+}
+
+/// 预签名 URL 及使用时须携带的请求头，expires_at 为 RFC 3339 格式
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct PresignedUrl {
+  method: String,
+  url: String,
+  headers: BTreeMap<String, String>,
+  expires_in: u64,
+  expires_at: String,
+}
+
+impl PresignedUrl {
+  fn new(req: &PresignedRequest, expires: Duration) -> Result<Self> {
+    let headers = req
+      .header()
+      .iter()
+      .map(|(name, value)| Ok((name.to_string(), value.to_str()?.to_string())))
+      .collect::<Result<_>>()?;
+    Ok(Self {
+      method: req.method().to_string(),
+      url: req.uri().to_string(),
+      headers,
+      expires_in: expires.as_secs(),
+      expires_at: (chrono::Utc::now() + chrono::Duration::from_std(expires)?).to_rfc3339(),
+    })
+  }
+}
+
+#[cfg(all(test, feature = "services-memory"))]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn test_presign_unsupported() -> Result<()> {
+    let op = Operator::new(opendal::services::Memory::default())?.finish();
+    let presign = PresignOperation::Get { object_key: "demo.txt".to_string(), expires: Duration::from_secs(60) };
+    let err = presign.execute(&op).await.unwrap_err();
+    assert!(err.to_string().contains("does not support presigned download url"));
+    Ok(())
+  }
+}
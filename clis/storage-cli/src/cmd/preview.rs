@@ -0,0 +1,203 @@
+use std::{fmt::Display, ops::Range, str::FromStr};
+
+use anyhow::{Result, bail};
+use futures::TryStreamExt;
+use opendal::Operator;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use super::{checksum::Hasher, progress::Progress};
+
+/// 字节范围，格式同 HTTP Range：`start-end`（包含 end）、`start-`（从 start 到末尾）、`-n`（最后 n 字节）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+  Bounded(u64, u64),
+  From(u64),
+  Suffix(u64),
+}
+
+impl ByteRange {
+  /// 按对象大小转换为左闭右开的字节区间，end 超出对象大小时截断
+  pub fn resolve(&self, size: u64) -> Result<Range<u64>> {
+    let range = match *self {
+      ByteRange::Bounded(start, end) => start..size.min(end.saturating_add(1)),
+      ByteRange::From(start) => start..size,
+      ByteRange::Suffix(n) => size.saturating_sub(n)..size,
+    };
+    if range.start > 0 && range.start >= size {
+      bail!("The range '{}' is out of the object size {}", self, size);
+    }
+    Ok(range)
+  }
+}
+
+impl FromStr for ByteRange {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let invalid = || format!("Invalid range '{}', expected 'start-end', 'start-' or '-n'", s);
+    let (start, end) = s.trim().split_once('-').ok_or_else(invalid)?;
+    let parse = |v: &str| v.trim().parse::<u64>().map_err(|_| invalid());
+    match (start.trim().is_empty(), end.trim().is_empty()) {
+      (false, false) => {
+        let (start, end) = (parse(start)?, parse(end)?);
+        if start > end {
+          return Err(invalid());
+        }
+        Ok(ByteRange::Bounded(start, end))
+      }
+      (false, true) => Ok(ByteRange::From(parse(start)?)),
+      (true, false) => Ok(ByteRange::Suffix(parse(end)?)),
+      (true, true) => Err(invalid()),
+    }
+  }
+}
+
+impl Display for ByteRange {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ByteRange::Bounded(start, end) => write!(f, "{}-{}", start, end),
+      ByteRange::From(start) => write!(f, "{}-", start),
+      ByteRange::Suffix(n) => write!(f, "-{}", n),
+    }
+  }
+}
+
+/// 读取对象的字节区间，未指定 range 时为整个对象
+pub(crate) async fn resolve_range(op: &Operator, object_key: &str, range: Option<ByteRange>) -> Result<Range<u64>> {
+  let size = op.stat(object_key).await?.content_length();
+  match range {
+    Some(range) => range.resolve(size),
+    None => Ok(0..size),
+  }
+}
+
+/// 将对象的字节区间流式写入 w，返回写入的字节数。指定 hasher 时同时计算校验和
+pub(super) async fn write_range_to<W: AsyncWrite + Unpin>(
+  op: &Operator,
+  object_key: &str,
+  range: Range<u64>,
+  w: &mut W,
+  mut hasher: Option<&mut Hasher>,
+  progress: &Progress,
+) -> Result<u64> {
+  if range.is_empty() {
+    return Ok(0);
+  }
+  let reader = op.reader_with(object_key).await?;
+  let mut bs = reader.into_bytes_stream(range).await?;
+  let mut written = 0u64;
+  while let Some(item) = bs.try_next().await? {
+    if let Some(hasher) = hasher.as_mut() {
+      hasher.update(&item);
+    }
+    w.write_all(&item).await?;
+    written += item.len() as u64;
+    progress.inc(item.len() as u64);
+  }
+  w.flush().await?;
+  progress.finish();
+  Ok(written)
+}
+
+/// 输出对象内容到 w，可指定字节范围
+pub(super) async fn cat_object_key<W: AsyncWrite + Unpin>(
+  op: &Operator,
+  object_key: &str,
+  range: Option<ByteRange>,
+  w: &mut W,
+) -> Result<()> {
+  let range = resolve_range(op, object_key, range).await?;
+  write_preview_to(op, object_key, range, w).await
+}
+
+/// 输出对象的前 lines 行到 w，指定 bytes 时改为输出前 bytes 字节。读取到足够的内容后即停止，不会下载整个对象
+pub(super) async fn head_object_key<W: AsyncWrite + Unpin>(
+  op: &Operator,
+  object_key: &str,
+  lines: usize,
+  bytes: Option<u64>,
+  w: &mut W,
+) -> Result<()> {
+  if let Some(bytes) = bytes {
+    let range = resolve_range(op, object_key, Some(ByteRange::From(0))).await?;
+    let range = range.start..range.end.min(bytes);
+    return write_preview_to(op, object_key, range, w).await;
+  }
+
+  let reader = op.reader_with(object_key).await?;
+  let mut bs = reader.into_bytes_stream(..).await?;
+  let mut remaining = lines;
+  let result: Result<()> = async {
+    while remaining > 0
+      && let Some(item) = bs.try_next().await?
+    {
+      let end = match item.iter().enumerate().filter(|(_, b)| **b == b'\n').nth(remaining - 1) {
+        Some((i, _)) => {
+          remaining = 0;
+          i + 1
+        }
+        None => {
+          remaining -= item.iter().filter(|b| **b == b'\n').count();
+          item.len()
+        }
+      };
+      w.write_all(&item[..end]).await?;
+    }
+    w.flush().await?;
+    Ok(())
+  }
+  .await;
+  ignore_broken_pipe(result)
+}
+
+/// 输出对象的最后 bytes 字节到 w，仅读取对象末尾的内容
+pub(super) async fn tail_object_key<W: AsyncWrite + Unpin>(
+  op: &Operator,
+  object_key: &str,
+  bytes: u64,
+  w: &mut W,
+) -> Result<()> {
+  let range = resolve_range(op, object_key, Some(ByteRange::Suffix(bytes))).await?;
+  write_preview_to(op, object_key, range, w).await
+}
+
+/// 预览对象的字节区间到 w，不显示进度
+async fn write_preview_to<W: AsyncWrite + Unpin>(
+  op: &Operator,
+  object_key: &str,
+  range: Range<u64>,
+  w: &mut W,
+) -> Result<()> {
+  let result = write_range_to(op, object_key, range, w, None, &Progress::hidden()).await;
+  ignore_broken_pipe(result.map(|_| ()))
+}
+
+/// 输出被提前关闭（如标准输出接 `| head`）时视为正常结束
+fn ignore_broken_pipe(result: Result<()>) -> Result<()> {
+  match result {
+    Err(e) if e.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe) => Ok(()),
+    r => r,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_byte_range() {
+    assert_eq!("0-1023".parse::<ByteRange>(), Ok(ByteRange::Bounded(0, 1023)));
+    assert_eq!("100-".parse::<ByteRange>(), Ok(ByteRange::From(100)));
+    assert_eq!("-500".parse::<ByteRange>(), Ok(ByteRange::Suffix(500)));
+    assert!("10-5".parse::<ByteRange>().is_err());
+    assert!("-".parse::<ByteRange>().is_err());
+    assert!("abc".parse::<ByteRange>().is_err());
+
+    assert_eq!(ByteRange::Bounded(0, 1023).resolve(100).unwrap(), 0..100);
+    assert_eq!(ByteRange::Bounded(10, 19).resolve(100).unwrap(), 10..20);
+    assert_eq!(ByteRange::From(90).resolve(100).unwrap(), 90..100);
+    assert_eq!(ByteRange::Suffix(500).resolve(100).unwrap(), 0..100);
+    assert_eq!(ByteRange::Suffix(10).resolve(100).unwrap(), 90..100);
+    assert!(ByteRange::From(100).resolve(100).is_err());
+  }
+}
@@ -0,0 +1,91 @@
+use std::{
+  io::IsTerminal,
+  sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+  },
+  time::Duration,
+};
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// 设置是否显示传输进度条，标准错误不是终端时始终不显示
+pub fn set_progress_enabled(enabled: bool) {
+  ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+fn is_enabled() -> bool {
+  ENABLED.load(Ordering::Relaxed) && std::io::stderr().is_terminal()
+}
+
+/// 传输进度，输出到标准错误。显示已传输字节数、百分比、速率与剩余时间，多文件传输时汇总显示
+#[derive(Clone)]
+pub(crate) struct Progress {
+  bar: ProgressBar,
+  done_files: Arc<AtomicUsize>,
+  total_files: usize,
+}
+
+impl Progress {
+  /// 单个文件的进度，total 未知时（如标准输入）仅显示已传输字节数与速率
+  pub(crate) fn new(name: &str, total: Option<u64>) -> Self {
+    let progress = Self::with_bar(total, 0);
+    progress.bar.set_message(name.to_string());
+    progress
+  }
+
+  /// 多个文件的汇总进度
+  pub(crate) fn files(total_files: usize, total_bytes: u64) -> Self {
+    let progress = Self::with_bar(Some(total_bytes), total_files);
+    progress.bar.set_message(format!("0/{} files", total_files));
+    progress
+  }
+
+  /// 不显示的进度
+  pub(crate) fn hidden() -> Self {
+    Self { bar: ProgressBar::hidden(), done_files: Arc::default(), total_files: 0 }
+  }
+
+  fn with_bar(total: Option<u64>, total_files: usize) -> Self {
+    if !is_enabled() {
+      return Self::hidden();
+    }
+    let bar = match total {
+      Some(total) => ProgressBar::with_draw_target(Some(total), ProgressDrawTarget::stderr()).with_style(
+        ProgressStyle::with_template(
+          "{msg} [{wide_bar}] {binary_bytes}/{binary_total_bytes} {percent}% {binary_bytes_per_sec} ETA {eta}",
+        )
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("=> "),
+      ),
+      None => ProgressBar::with_draw_target(None, ProgressDrawTarget::stderr()).with_style(
+        ProgressStyle::with_template("{spinner} {msg} {binary_bytes} {binary_bytes_per_sec} {elapsed}")
+          .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+      ),
+    };
+    bar.enable_steady_tick(Duration::from_millis(200));
+    Self { bar, done_files: Arc::default(), total_files }
+  }
+
+  pub(crate) fn inc(&self, n: u64) {
+    self.bar.inc(n);
+  }
+
+  /// 设置已传输的字节数，用于续传时跳过已完成的部分
+  pub(crate) fn set_position(&self, pos: u64) {
+    self.bar.set_position(pos);
+  }
+
+  /// 一个文件传输结束（含跳过与失败），更新汇总进度中的文件数
+  pub(crate) fn file_done(&self) {
+    let done = self.done_files.fetch_add(1, Ordering::Relaxed) + 1;
+    self.bar.set_message(format!("{}/{} files", done, self.total_files));
+  }
+
+  /// 传输结束后清除进度条
+  pub(crate) fn finish(&self) {
+    self.bar.finish_and_clear();
+  }
+}
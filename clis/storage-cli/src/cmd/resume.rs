@@ -0,0 +1,543 @@
+use std::{
+  collections::BTreeMap,
+  io::SeekFrom,
+  path::{Path, PathBuf},
+  time::UNIX_EPOCH,
+};
+
+use anyhow::{Result, bail};
+use bytes::Bytes;
+use futures::{StreamExt, TryStreamExt};
+use log::{info, warn};
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio::{
+  fs::{File, OpenOptions},
+  io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
+
+use super::{
+  MultipartUploader,
+  checksum::{file_checksums, verify_object},
+  file_operation::{UploadOptions, put_src_to_object_key, transfer_result},
+  progress::Progress,
+};
+use crate::client::{StorageClient, TransferResult};
+
+/// 下载的续传日志，记录开始下载时对象的状态，用于续传前校验
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct TransferJournal {
+  object_key: String,
+  local: String,
+  /// 对象大小
+  size: u64,
+  /// 对象的 etag
+  etag: Option<String>,
+}
+
+/// 分段上传的续传日志，记录开始上传时本地文件的状态、upload id 与已完成的段
+#[derive(Debug, Serialize, Deserialize)]
+struct UploadJournal {
+  object_key: String,
+  local: String,
+  /// 本地文件大小
+  size: u64,
+  /// 本地文件的修改时间（秒）
+  modified: Option<u64>,
+  upload_id: String,
+  part_size: u64,
+  /// 已完成的段号与 etag
+  parts: BTreeMap<u32, String>,
+}
+
+impl UploadJournal {
+  /// 是否为同一本地文件到同一对象的上传，且本地文件未变更
+  fn is_same_upload(&self, other: &UploadJournal) -> bool {
+    self.object_key == other.object_key
+      && self.local == other.local
+      && self.size == other.size
+      && self.modified == other.modified
+  }
+
+  fn part_count(&self) -> u32 {
+    self.size.div_ceil(self.part_size) as u32
+  }
+
+  /// 第 n 段（从 1 开始）的字节数，最后一段可能小于 part_size
+  fn part_len(&self, n: u32) -> u64 {
+    self.part_size.min(self.size - (n as u64 - 1) * self.part_size)
+  }
+}
+
+/// 默认的日志目录：`$XDG_STATE_HOME/devops-cli/journal`，未设置时使用 `~/.local/state`，均不可用时使用系统临时目录
+pub(crate) fn default_journal_dir() -> PathBuf {
+  let state_dir = std::env::var_os("XDG_STATE_HOME")
+    .filter(|v| !v.is_empty())
+    .map(PathBuf::from)
+    .or_else(|| std::env::var_os("HOME").filter(|v| !v.is_empty()).map(|h| PathBuf::from(h).join(".local/state")))
+    .unwrap_or_else(std::env::temp_dir);
+  state_dir.join("devops-cli").join("journal")
+}
+
+/// 日志文件路径：`<dir>/<kind>-<md5(local, object_key)>.json`
+fn journal_path(dir: &Path, kind: &str, local: &str, object_key: &str) -> PathBuf {
+  let id = format!("{:x}", Md5::digest(format!("{}\n{}", local, object_key)));
+  dir.join(format!("{}-{}.json", kind, id))
+}
+
+async fn load_journal<T: DeserializeOwned>(path: &Path) -> Option<T> {
+  let content = tokio::fs::read(path).await.ok()?;
+  serde_json::from_slice(&content).ok()
+}
+
+async fn save_journal<T: Serialize>(journal: &T, path: &Path) -> Result<()> {
+  if let Some(parent) = path.parent() {
+    tokio::fs::create_dir_all(parent).await?;
+  }
+  tokio::fs::write(path, serde_json::to_vec(journal)?).await?;
+  Ok(())
+}
+
+/// 断点续传下载：存在续传日志且对象未变更（大小与 etag 一致）时，从本地文件末尾继续下载，对象已变更时重新下载。
+/// 本地文件已存在但没有续传日志时，无法确认是该对象的部分内容（可能是旧版本或其它文件），overwrite 时重新下载，
+/// 否则返回错误。返回结果的 bytes 为本次下载的字节数，校验和为整个本地文件的校验和
+pub(super) async fn get_resume(
+  client: &StorageClient,
+  object_key: &str,
+  dst: &str,
+  overwrite: bool,
+) -> Result<TransferResult> {
+  let op = client.operator();
+  let md = op.stat(object_key).await?;
+  let journal = TransferJournal {
+    object_key: object_key.to_string(),
+    local: dst.to_string(),
+    size: md.content_length(),
+    etag: md.etag().map(|s| s.to_string()),
+  };
+  let journal_path = journal_path(&client.journal_dir(), "get", dst, object_key);
+
+  let local_len = tokio::fs::metadata(dst).await.map(|m| m.len()).ok();
+  let offset = match (local_len, load_journal::<TransferJournal>(&journal_path).await) {
+    (Some(len), Some(saved)) if saved == journal && len <= journal.size => len,
+    (Some(_), Some(_)) => {
+      warn!("The object '{}' has changed since the last download, restart from the beginning.", object_key);
+      0
+    }
+    (Some(_), None) if overwrite => {
+      warn!("No download journal of '{}' is found, restart from the beginning.", dst);
+      0
+    }
+    (Some(_), None) => bail!("The file '{}' exists without a download journal, use --overwrite to download again", dst),
+    (None, _) => 0,
+  };
+  save_journal(&journal, &journal_path).await?;
+
+  let mut f = OpenOptions::new().create(true).write(true).truncate(false).open(dst).await?;
+  f.set_len(offset).await?;
+  f.seek(SeekFrom::Start(offset)).await?;
+
+  let mut readed = 0u64;
+  let progress = Progress::new(object_key, Some(journal.size));
+  progress.inc(offset);
+  if offset < journal.size {
+    info!("Resume download of '{}' from {} bytes.", object_key, offset);
+    let reader = op.reader_with(object_key).await?;
+    let mut bs = reader.into_bytes_stream(offset..).await?;
+    while let Some(item) = bs.try_next().await? {
+      readed += item.len() as u64;
+      f.write_all(&item).await?;
+      progress.inc(item.len() as u64);
+    }
+  }
+  f.flush().await?;
+  progress.finish();
+
+  if offset + readed != journal.size {
+    bail!("Download of '{}' is incomplete, {} of {} bytes", object_key, offset + readed, journal.size);
+  }
+  tokio::fs::remove_file(&journal_path).await.ok();
+  info!("Total file download of {} bytes, {} bytes resumed.", readed, offset);
+  // 续传的内容分多次下载，完成后重新计算整个本地文件的校验和
+  let checksums = file_checksums(Path::new(dst)).await?;
+  if let Err(e) = verify_object(client, object_key, &checksums).await {
+    // 删除校验失败的文件，避免被当作完整的文件使用
+    tokio::fs::remove_file(dst).await.ok();
+    return Err(e);
+  }
+  Ok(transfer_result(object_key, readed, checksums))
+}
+
+/// 断点续传上传：大于一段的文件分段上传，续传日志记录 upload id 与已完成的段，本地文件未变更时仅上传剩余的段。
+/// 需要存储支持 S3 兼容的分段上传接口（OBS、OSS、S3、COS）。返回结果的 bytes 为本次上传的字节数
+pub(super) async fn put_resume(
+  client: &StorageClient,
+  src: &str,
+  object_key: &str,
+  upload: &UploadOptions,
+) -> Result<TransferResult> {
+  let op = client.operator();
+  let Some(mp) = client.multipart() else {
+    bail!(
+      "The storage '{}' does not support resumable upload, only obs, oss, s3 and cos are supported",
+      op.info().scheme()
+    )
+  };
+  let size = tokio::fs::metadata(src).await?.len();
+  let progress = Progress::new(object_key, Some(size));
+  if size <= mp.part_size(size, upload.chunk_size) {
+    // 不大于一段的文件只需一次请求，无需续传
+    let result = put_src_to_object_key(client, src, object_key, upload, &progress).await;
+    progress.finish();
+    return result;
+  }
+
+  let journal_path = journal_path(&client.journal_dir(), "put", src, object_key);
+  let result = multipart_upload(mp, src, object_key, upload, Some(&journal_path), &progress).await;
+  progress.finish();
+  let (uploaded, resumed) = result?;
+  info!("Total file upload of {} bytes, {} bytes resumed.", uploaded, resumed);
+  let checksums = file_checksums(Path::new(src)).await?;
+  verify_object(client, object_key, &checksums).await?;
+  Ok(transfer_result(object_key, uploaded, checksums))
+}
+
+/// 分段上传并合并，返回本次上传与续传跳过的字节数。指定 journal_path 时按续传日志继续上传，分段上传已不存在
+/// （如过期被清理）时重新上传
+pub(super) async fn multipart_upload(
+  mp: &MultipartUploader,
+  src: &str,
+  object_key: &str,
+  upload: &UploadOptions,
+  journal_path: Option<&Path>,
+  progress: &Progress,
+) -> Result<(u64, u64)> {
+  let local_md = tokio::fs::metadata(src).await?;
+  let mut journal = UploadJournal {
+    object_key: object_key.to_string(),
+    local: src.to_string(),
+    size: local_md.len(),
+    modified: local_md.modified().ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs()),
+    upload_id: String::new(),
+    part_size: mp.part_size(local_md.len(), upload.chunk_size),
+    parts: BTreeMap::new(),
+  };
+  let saved = match journal_path {
+    Some(path) => load_journal::<UploadJournal>(path).await,
+    None => None,
+  };
+  match saved {
+    Some(saved) if saved.is_same_upload(&journal) => journal = saved,
+    Some(saved) => {
+      warn!("The file '{}' has changed since the last upload, restart from the beginning.", src);
+      if let Err(e) = mp.abort(&saved.object_key, &saved.upload_id).await {
+        warn!("Abort the previous upload of '{}' failed: {}", saved.object_key, e);
+      }
+    }
+    None => {}
+  }
+
+  let mut restarted = false;
+  loop {
+    if journal.upload_id.is_empty() {
+      journal.upload_id = mp.initiate(object_key, &upload.headers_of(src, mp)).await?;
+      journal.parts.clear();
+      if let Some(path) = journal_path {
+        save_journal(&journal, path).await?;
+      }
+    }
+    let resumed = journal.parts.keys().map(|n| journal.part_len(*n)).sum::<u64>();
+    progress.set_position(resumed);
+    if resumed > 0 {
+      info!(
+        "Resume upload of '{}', {} of {} parts are uploaded.",
+        object_key,
+        journal.parts.len(),
+        journal.part_count()
+      );
+    }
+
+    let result = async {
+      let uploaded = upload_parts(mp, &mut journal, upload.concurrency, journal_path, progress).await?;
+      mp.complete(object_key, &journal.upload_id, &journal.parts).await?;
+      Ok(uploaded)
+    };
+    match result.await {
+      Ok(uploaded) => {
+        if let Some(path) = journal_path {
+          tokio::fs::remove_file(path).await.ok();
+        }
+        return Ok((uploaded, resumed));
+      }
+      Err(e) if !restarted && MultipartUploader::is_no_such_upload(&e) => {
+        warn!("The multipart upload of '{}' is not found, restart from the beginning.", object_key);
+        journal.upload_id.clear();
+        restarted = true;
+      }
+      Err(e) => return Err(e),
+    }
+  }
+}
+
+/// 并发上传未完成的段，每完成一段保存一次续传日志，返回本次上传的字节数
+async fn upload_parts(
+  mp: &MultipartUploader,
+  journal: &mut UploadJournal,
+  concurrency: usize,
+  journal_path: Option<&Path>,
+  progress: &Progress,
+) -> Result<u64> {
+  let pending = (1..=journal.part_count())
+    .filter(|n| !journal.parts.contains_key(n))
+    .map(|n| (n, journal.part_len(n)))
+    .collect::<Vec<_>>();
+  let (src, object_key, upload_id) = (journal.local.clone(), journal.object_key.clone(), journal.upload_id.clone());
+  let part_size = journal.part_size;
+
+  let mut parts = futures::stream::iter(pending)
+    .map(|(n, len)| {
+      let (src, object_key, upload_id) = (&src, &object_key, &upload_id);
+      async move {
+        let mut f = File::open(src).await?;
+        f.seek(SeekFrom::Start((n as u64 - 1) * part_size)).await?;
+        let mut buf = vec![0_u8; len as usize];
+        f.read_exact(&mut buf).await?;
+        let etag = mp.upload_part(object_key, upload_id, n, Bytes::from(buf)).await?;
+        anyhow::Ok((n, len, etag))
+      }
+    })
+    .buffer_unordered(concurrency.max(1));
+
+  let mut uploaded = 0;
+  while let Some((n, len, etag)) = parts.try_next().await? {
+    journal.parts.insert(n, etag);
+    if let Some(path) = journal_path {
+      save_journal(journal, path).await?;
+    }
+    uploaded += len;
+    progress.inc(len);
+  }
+  Ok(uploaded)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+  };
+
+  use tokio::{
+    io::AsyncBufReadExt,
+    net::{TcpListener, TcpStream},
+  };
+
+  use base64::Engine;
+  use opendal::Operator;
+
+  use super::*;
+  use crate::{
+    cmd::{HttpClient, StorageSource},
+    conf::{StorageConf, TransferConf},
+  };
+
+  #[tokio::test]
+  async fn test_get_resume() -> Result<()> {
+    let op = Operator::new(opendal::services::Memory::default())?.finish();
+    let content = (0..10_000u32).flat_map(|i| i.to_le_bytes()).collect::<Vec<_>>();
+    op.write("demo/app.bin", content.clone()).await?;
+    let dir = std::env::temp_dir().join(format!("storage-cli-resume-test-{}", std::process::id()));
+    tokio::fs::create_dir_all(&dir).await?;
+    let client = StorageClient::from_operator(op.clone()).with_journal_dir(dir.join("journal"));
+    let dst = dir.join("app.bin").to_string_lossy().to_string();
+    let journal_path = journal_path(&dir.join("journal"), "get", &dst, "demo/app.bin");
+
+    // 没有续传日志时，本地的旧文件不能作为对象的部分内容，未指定 overwrite 时失败且不修改本地文件
+    tokio::fs::write(&dst, vec![0u8; 4096]).await?;
+    assert!(get_resume(&client, "demo/app.bin", &dst, false).await.is_err());
+    assert_eq!(tokio::fs::read(&dst).await?, vec![0u8; 4096]);
+    assert!(!journal_path.exists());
+    assert_eq!(get_resume(&client, "demo/app.bin", &dst, true).await?.bytes, content.len() as u64);
+    assert_eq!(tokio::fs::read(&dst).await?, content);
+    assert!(!journal_path.exists());
+
+    // 续传日志与对象一致时，从本地文件末尾继续下载
+    let md = op.stat("demo/app.bin").await?;
+    let journal = TransferJournal {
+      object_key: "demo/app.bin".to_string(),
+      local: dst.clone(),
+      size: md.content_length(),
+      etag: md.etag().map(|s| s.to_string()),
+    };
+    save_journal(&journal, &journal_path).await?;
+    tokio::fs::write(&dst, &content[..4096]).await?;
+    assert_eq!(get_resume(&client, "demo/app.bin", &dst, false).await?.bytes, content.len() as u64 - 4096);
+    assert_eq!(tokio::fs::read(&dst).await?, content);
+
+    tokio::fs::remove_dir_all(dir).await?;
+    Ok(())
+  }
+
+  /// 模拟的分段上传服务状态，fail_part 指定的段返回 500
+  #[derive(Default)]
+  struct MockState {
+    parts: BTreeMap<u32, Vec<u8>>,
+    /// 按请求顺序记录成功上传的段号
+    uploaded: Vec<u32>,
+    object: Option<Vec<u8>>,
+    /// 最近一次请求的自定义元数据请求头
+    meta: Vec<String>,
+    fail_part: Option<u32>,
+  }
+
+  /// 启动模拟 OSS 分段上传接口的 HTTP 服务，不校验签名
+  async fn mock_server(state: Arc<Mutex<MockState>>) -> Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+      while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(serve(stream, state.clone()));
+      }
+    });
+    Ok(addr)
+  }
+
+  async fn serve(stream: TcpStream, state: Arc<Mutex<MockState>>) -> Result<()> {
+    let mut stream = tokio::io::BufReader::new(stream);
+    loop {
+      let mut line = String::new();
+      if stream.read_line(&mut line).await? == 0 {
+        return Ok(());
+      }
+      let mut parts = line.split(' ');
+      let (method, target) =
+        (parts.next().unwrap_or_default().to_string(), parts.next().unwrap_or_default().to_string());
+      let (mut len, mut md5, mut meta) = (0, None, Vec::new());
+      loop {
+        let mut header = String::new();
+        stream.read_line(&mut header).await?;
+        if header.trim().is_empty() {
+          break;
+        }
+        match header.split_once(':') {
+          Some((name, value)) if name.eq_ignore_ascii_case("content-length") => len = value.trim().parse()?,
+          Some((name, value)) if name.eq_ignore_ascii_case("content-md5") => md5 = Some(value.trim().to_string()),
+          Some((name, value)) if name.starts_with("x-oss-meta-") => meta.push(format!("{}: {}", name, value.trim())),
+          _ => {}
+        }
+      }
+      let mut body = vec![0_u8; len];
+      stream.read_exact(&mut body).await?;
+
+      let (status, headers, resp) = {
+        let mut state = state.lock().unwrap();
+        state.meta = meta;
+        handle(&method, &target, body, md5, &mut state)
+      };
+      let head = format!("HTTP/1.1 {}\r\ncontent-length: {}\r\n{}\r\n", status, resp.len(), headers);
+      stream.get_mut().write_all(head.as_bytes()).await?;
+      stream.get_mut().write_all(resp.as_bytes()).await?;
+    }
+  }
+
+  /// 上传对象与分段时校验 Content-MD5 请求头，缺失或不一致时返回 400
+  fn handle(
+    method: &str,
+    target: &str,
+    body: Vec<u8>,
+    md5: Option<String>,
+    state: &mut MockState,
+  ) -> (&'static str, String, String) {
+    let query = target.split_once('?').map(|(_, q)| q).unwrap_or_default();
+    let param = |name: &str| query.split('&').find_map(|kv| kv.strip_prefix(&format!("{}=", name)));
+    let expected = base64::engine::general_purpose::STANDARD.encode(Md5::digest(&body));
+    match method {
+      "PUT" if md5.as_deref() != Some(expected.as_str()) => {
+        let resp = "<Error><Code>BadDigest</Code><Message>mock error</Message></Error>";
+        ("400 Bad Request", String::new(), resp.to_string())
+      }
+      "PUT" if query.is_empty() => {
+        state.object = Some(body);
+        ("200 OK", "etag: \"object\"\r\n".to_string(), String::new())
+      }
+      "POST" if query == "uploads" => {
+        let resp = "<InitiateMultipartUploadResult><UploadId>upload-1</UploadId></InitiateMultipartUploadResult>";
+        ("200 OK", String::new(), resp.to_string())
+      }
+      "PUT" => {
+        let n = param("partNumber").and_then(|n| n.parse().ok()).unwrap_or_default();
+        if state.fail_part == Some(n) {
+          let resp = "<Error><Code>InternalError</Code><Message>mock error</Message></Error>";
+          return ("500 Internal Server Error", String::new(), resp.to_string());
+        }
+        state.uploaded.push(n);
+        state.parts.insert(n, body);
+        ("200 OK", format!("etag: \"part-{}\"\r\n", n), String::new())
+      }
+      "POST" => {
+        let xml = String::from_utf8_lossy(&body);
+        let numbers = xml.split("<PartNumber>").skip(1).filter_map(|s| s.split('<').next()?.parse::<u32>().ok());
+        state.object = Some(numbers.flat_map(|n| state.parts[&n].clone()).collect());
+        ("200 OK", String::new(), "<CompleteMultipartUploadResult/>".to_string())
+      }
+      _ => ("400 Bad Request", String::new(), String::new()),
+    }
+  }
+
+  #[tokio::test]
+  async fn test_multipart_upload_resume() -> Result<()> {
+    let state = Arc::new(Mutex::new(MockState { fail_part: Some(2), ..Default::default() }));
+    let addr = mock_server(state.clone()).await?;
+    let mut sc = StorageConf::default();
+    sc.endpoint = format!("http://oss.test:{}", addr.port());
+    sc.bucket = "demo".into();
+    let tc = TransferConf { max_retries: 0, ..Default::default() };
+    let client = reqwest::Client::builder().resolve("demo.oss.test", addr).build()?;
+    let http = HttpClient::new(&tc)?;
+    let mp = MultipartUploader::new(&StorageSource::Oss, &sc, &http, &tc)?
+      .expect("oss supports multipart")
+      .with_client(client);
+
+    let dir = std::env::temp_dir().join(format!("storage-cli-multipart-test-{}", std::process::id()));
+    tokio::fs::create_dir_all(&dir).await?;
+    let src = dir.join("app.bin").to_string_lossy().to_string();
+    let part_size = 5 * 1024 * 1024;
+    let content = (0..(part_size * 2 + 1000) / 4).flat_map(|i| (i as u32).to_le_bytes()).collect::<Vec<_>>();
+    tokio::fs::write(&src, &content).await?;
+    let journal_path = dir.join("journal.json");
+    let upload = UploadOptions { chunk_size: 1, concurrency: 1, ..Default::default() };
+    let progress = Progress::hidden();
+    let path = Some(journal_path.as_path());
+
+    // 第 2 段上传失败时中断，续传日志保留已完成的第 1 段
+    let err = multipart_upload(&mp, &src, "demo/app.bin", &upload, path, &progress).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("InternalError"));
+    let journal = load_journal::<UploadJournal>(&journal_path).await.expect("journal is saved");
+    assert_eq!((journal.upload_id.as_str(), journal.part_size), ("upload-1", part_size as u64));
+    assert_eq!(journal.parts.keys().copied().collect::<Vec<_>>(), [1]);
+
+    // 重新执行时仅上传剩余的段，合并后的对象与本地文件一致
+    state.lock().unwrap().fail_part = None;
+    let (uploaded, resumed) = multipart_upload(&mp, &src, "demo/app.bin", &upload, path, &progress).await?;
+    assert_eq!((uploaded, resumed), ((content.len() - part_size) as u64, part_size as u64));
+    {
+      let state = state.lock().unwrap();
+      assert_eq!(state.uploaded, [1, 2, 3]);
+      assert_eq!(state.object.as_deref(), Some(&content[..]));
+    }
+    assert!(!journal_path.exists());
+
+    // 单次请求上传带 Content-MD5 与自定义元数据请求头
+    let upload = UploadOptions { meta: vec![("build-id".into(), "42".into())], ..Default::default() };
+    let headers = upload.headers_of("small.txt", &mp);
+    mp.put_object("demo/small.txt", &headers, Bytes::from_static(b"hello")).await?;
+    {
+      let state = state.lock().unwrap();
+      assert_eq!(state.object.as_deref(), Some(&b"hello"[..]));
+      assert_eq!(state.meta, ["x-oss-meta-build-id: 42"]);
+    }
+
+    tokio::fs::remove_dir_all(dir).await?;
+    Ok(())
+  }
+}
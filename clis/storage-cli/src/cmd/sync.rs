@@ -0,0 +1,261 @@
+use std::{
+  collections::BTreeMap,
+  path::{Path, PathBuf},
+  time::SystemTime,
+};
+
+use anyhow::{Result, anyhow, bail};
+use clap::ValueEnum;
+use futures::{StreamExt, TryStreamExt};
+use log::info;
+use opendal::{Metadata, Metakey, Operator, Scheme};
+use serde::Serialize;
+
+use super::{
+  checksum::{file_checksums, remote_md5_hex},
+  file_operation::{
+    ExistingPolicy, TransferSummary, UploadOptions, confirm_delete, get_object_key_to_path, join_object_key,
+    local_path_of, put_src_to_object_key, walk_dir,
+  },
+  output::print_structured,
+  progress::Progress,
+};
+use crate::client::StorageClient;
+
+/// 同步方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SyncDirection {
+  /// 本地目录同步到对象存储
+  Up,
+  /// 对象存储同步到本地目录
+  Down,
+}
+
+/// 本地文件信息
+struct LocalFile {
+  path: PathBuf,
+  size: u64,
+  modified: Option<SystemTime>,
+}
+
+/// 同步计划：需要传输的文件与需要删除的目标端文件，均为相对路径
+#[derive(Debug, Default, Serialize)]
+struct SyncPlan {
+  transfers: Vec<String>,
+  deletes: Vec<String>,
+}
+
+/// 在本地目录与对象存储前缀之间单向增量同步
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn sync_dir(
+  client: &StorageClient,
+  local: &str,
+  prefix: &str,
+  direction: SyncDirection,
+  delete: bool,
+  yes: bool,
+  dry_run: bool,
+  jobs: usize,
+  upload: &UploadOptions,
+) -> Result<()> {
+  let op = client.operator();
+  let root = Path::new(local);
+  let prefix = match prefix.trim_end_matches('/') {
+    "" => "/".to_string(),
+    p => format!("{}/", p),
+  };
+  // 源目录不存在（如路径写错）时会被视为空目录，--delete 将清空目标端
+  if direction == SyncDirection::Up && !tokio::fs::try_exists(root).await? {
+    bail!("The local directory '{}' does not exist", local);
+  }
+  if delete && direction == SyncDirection::Up && prefix == "/" {
+    bail!("Refusing to sync with --delete to the whole bucket");
+  }
+
+  let locals = list_local(root).await?;
+  let remotes = list_remote(op, &prefix).await?;
+  let plan = make_plan(&locals, &remotes, op.info().scheme(), direction, delete).await?;
+
+  let (action, target) = match direction {
+    SyncDirection::Up => ("upload", &prefix),
+    SyncDirection::Down => ("download", &local.to_string()),
+  };
+  if dry_run {
+    if !print_structured(&plan)? {
+      for rel in plan.transfers.iter() {
+        println!("{}: {}", action, rel);
+      }
+      for rel in plan.deletes.iter() {
+        println!("delete: {}", rel);
+      }
+    }
+    info!(
+      "Dry run: {} files to {}, {} files to delete in '{}'.",
+      plan.transfers.len(),
+      action,
+      plan.deletes.len(),
+      target
+    );
+    return Ok(());
+  }
+  if !plan.deletes.is_empty() && !confirm_delete(yes, plan.deletes.len(), target)? {
+    info!("Sync cancelled.");
+    return Ok(());
+  }
+
+  let total_bytes = plan
+    .transfers
+    .iter()
+    .map(|rel| match direction {
+      SyncDirection::Up => locals.get(rel).map_or(0, |f| f.size),
+      SyncDirection::Down => remotes.get(rel).map_or(0, |md| md.content_length()),
+    })
+    .sum();
+  let progress = Progress::files(plan.transfers.len(), total_bytes);
+  let mut transfers = futures::stream::iter(plan.transfers)
+    .map(|rel| {
+      let prefix = &prefix;
+      let progress = &progress;
+      async move {
+        let object_key = join_object_key(prefix, Path::new(&rel));
+        let result = match direction {
+          SyncDirection::Up => {
+            let path = root.join(&rel);
+            let src = path.to_string_lossy();
+            put_src_to_object_key(client, &src, &object_key, upload, progress).await.map(|r| r.bytes)
+          }
+          SyncDirection::Down => match local_path_of(root, &rel) {
+            Some(path) => get_object_key_to_path(client, &object_key, &path, ExistingPolicy::Overwrite, progress)
+              .await
+              .map(|r| r.map_or(0, |r| r.bytes)),
+            None => Err(anyhow!("The object key can not be mapped to a local path")),
+          },
+        };
+        progress.file_done();
+        (rel, result)
+      }
+    })
+    .buffer_unordered(jobs.max(1));
+
+  let mut summary = if delete { TransferSummary::with_deletes() } else { TransferSummary::default() };
+  while let Some((rel, result)) = transfers.next().await {
+    summary.add(result, &rel);
+  }
+  progress.finish();
+
+  for rel in plan.deletes.iter() {
+    let result: Result<()> = match direction {
+      SyncDirection::Up => op.delete(&join_object_key(&prefix, Path::new(rel))).await.map_err(Into::into),
+      SyncDirection::Down => match locals.get(rel) {
+        Some(local) => tokio::fs::remove_file(&local.path).await.map_err(Into::into),
+        None => Err(anyhow!("The local file is not found")),
+      },
+    };
+    summary.add_delete(result, rel);
+  }
+
+  summary.finish()
+}
+
+/// 列出本地目录下的所有文件，key 为以 `/` 分隔的相对路径。目录不存在时返回空
+async fn list_local(root: &Path) -> Result<BTreeMap<String, LocalFile>> {
+  let mut locals = BTreeMap::new();
+  if !tokio::fs::try_exists(root).await? {
+    return Ok(locals);
+  }
+  for (path, md) in walk_dir(root).await? {
+    let rel = join_object_key("", path.strip_prefix(root).unwrap_or(&path));
+    locals.insert(rel, LocalFile { path, size: md.len(), modified: md.modified().ok() });
+  }
+  Ok(locals)
+}
+
+/// 列出对象存储前缀下的所有对象，key 为相对 prefix 的路径
+async fn list_remote(op: &Operator, prefix: &str) -> Result<BTreeMap<String, Metadata>> {
+  let lister = op
+    .lister_with(prefix)
+    .recursive(true)
+    .metakey(Metakey::Mode | Metakey::ContentLength | Metakey::LastModified | Metakey::Etag | Metakey::ContentMd5)
+    .await?;
+  let remotes = lister
+    .try_filter_map(|entry| async move {
+      if !entry.metadata().is_file() {
+        return Ok(None);
+      }
+      let (path, md) = entry.into_parts();
+      let rel = path.strip_prefix(prefix.trim_start_matches('/')).unwrap_or(&path).to_string();
+      Ok(Some((rel, md)))
+    })
+    .try_collect()
+    .await?;
+  Ok(remotes)
+}
+
+async fn make_plan(
+  locals: &BTreeMap<String, LocalFile>,
+  remotes: &BTreeMap<String, Metadata>,
+  scheme: Scheme,
+  direction: SyncDirection,
+  delete: bool,
+) -> Result<SyncPlan> {
+  let mut plan = SyncPlan::default();
+  match direction {
+    SyncDirection::Up => {
+      for (rel, local) in locals.iter() {
+        if is_changed(local, remotes.get(rel), scheme, direction).await? {
+          plan.transfers.push(rel.clone());
+        }
+      }
+      if delete {
+        plan.deletes = remotes.keys().filter(|rel| !locals.contains_key(*rel)).cloned().collect();
+      }
+    }
+    SyncDirection::Down => {
+      for (rel, remote) in remotes.iter() {
+        let changed = match locals.get(rel) {
+          Some(local) => is_changed(local, Some(remote), scheme, direction).await?,
+          None => true,
+        };
+        if changed {
+          plan.transfers.push(rel.clone());
+        }
+      }
+      if delete {
+        plan.deletes = locals.keys().filter(|rel| !remotes.contains_key(*rel)).cloned().collect();
+      }
+    }
+  }
+  Ok(plan)
+}
+
+/// 比较本地文件与远端对象是否不同：
+/// 1. 大小不同视为变更；
+/// 2. 远端提供 MD5（content-md5 或 OBS、S3 单段上传的 etag）时比较本地文件 MD5；
+/// 3. 否则比较修改时间，源端较新视为变更。
+async fn is_changed(
+  local: &LocalFile,
+  remote: Option<&Metadata>,
+  scheme: Scheme,
+  direction: SyncDirection,
+) -> Result<bool> {
+  let Some(remote) = remote else {
+    return Ok(true);
+  };
+  if local.size != remote.content_length() {
+    return Ok(true);
+  }
+
+  if let Some(remote_md5) = remote_md5_hex(remote, scheme) {
+    return Ok(file_checksums(&local.path).await?.md5 != remote_md5);
+  }
+
+  let remote_modified = remote.last_modified().map(SystemTime::from);
+  let changed = match (local.modified, remote_modified) {
+    (Some(l), Some(r)) => match direction {
+      SyncDirection::Up => l > r,
+      SyncDirection::Down => r > l,
+    },
+    _ => true,
+  };
+  Ok(changed)
+}
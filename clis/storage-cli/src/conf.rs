@@ -1,70 +1,353 @@
-use anyhow::Result;
-use config::{ConfigBuilder, FileFormat, builder::DefaultState};
-use serde::Deserialize;
+use std::{collections::HashMap, path::PathBuf, sync::OnceLock};
+
+use anyhow::{Result, anyhow, bail};
+use config::{ConfigBuilder, ConfigError, FileFormat, Map, Source, Value, ValueKind, builder::DefaultState};
+use serde::{Deserialize, Deserializer};
 
 use crate::{
   cmd::{DevopsCmd, StorageSource},
-  utils::set_env,
+  credentials::{Credentials, check_file_permission, expand_home},
+  operators::check_security_token_env,
+  utils::{mask_secret, parse_size},
 };
 
 #[derive(Debug, Deserialize)]
 pub struct DevopsConf {
   service: StorageSource,
   storage: Option<StorageConf>,
+  /// 命名存储配置，对应配置文件中的 `[storages.<name>]`
+  #[serde(default)]
+  storages: HashMap<String, StorageConf>,
+  /// 传输配置，对应配置文件中的 `[transfer]`
+  #[serde(default)]
+  transfer: TransferConf,
+  /// 已加载的配置文件，按优先级从低到高排列
+  #[serde(skip)]
+  config_files: Vec<PathBuf>,
 }
 impl DevopsConf {
   pub fn service(&self) -> &StorageSource {
     &self.service
   }
 
+  /// 存储配置使用的存储服务，其 `service` 未设置时使用顶层的 `service`
+  pub fn service_of<'a>(&'a self, sc: &'a StorageConf) -> &'a StorageSource {
+    sc.service.as_ref().unwrap_or(&self.service)
+  }
+
   pub fn storage(&self) -> Option<&StorageConf> {
     self.storage.as_ref()
   }
+
+  pub fn named_storage(&self, name: &str) -> Option<&StorageConf> {
+    self.storages.get(name)
+  }
+
+  pub fn config_files(&self) -> &[PathBuf] {
+    &self.config_files
+  }
+
+  pub fn transfer(&self) -> &TransferConf {
+    &self.transfer
+  }
 }
 
-#[derive(Debug, Deserialize)]
+/// 重试、超时与带宽限制配置，应用于所有存储的 Operator
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TransferConf {
+  /// 请求失败后的最大重试次数，0 为不重试
+  pub max_retries: usize,
+  /// 首次重试前等待的秒数，之后按指数退避
+  pub retry_min_delay: f64,
+  /// 重试前等待的最大秒数
+  pub retry_max_delay: f64,
+  /// 单个请求的超时秒数
+  pub timeout: u64,
+  /// 读写数据时单次 IO 的超时秒数
+  pub io_timeout: u64,
+  /// 每秒读写的最大字节数，支持 K/KiB、M/MiB 等单位，未设置时不限制
+  #[serde(deserialize_with = "deserialize_size")]
+  pub bandwidth: Option<usize>,
+  /// 断点续传日志的目录，支持 `~/` 开头，未设置时使用 `$XDG_STATE_HOME/devops-cli/journal`
+  pub journal_dir: Option<String>,
+}
+
+impl Default for TransferConf {
+  fn default() -> Self {
+    Self {
+      max_retries: 3,
+      retry_min_delay: 1.0,
+      retry_max_delay: 30.0,
+      timeout: 60,
+      io_timeout: 60,
+      bandwidth: None,
+      journal_dir: None,
+    }
+  }
+}
+
+/// 反序列化大小，支持整数字节数或带单位的字符串（如 `10MiB`）
+fn deserialize_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<usize>, D::Error> {
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum Size {
+    Bytes(usize),
+    Text(String),
+  }
+  match Option::<Size>::deserialize(deserializer)? {
+    Some(Size::Bytes(n)) => Ok(Some(n)),
+    Some(Size::Text(s)) => parse_size(&s).map(Some).map_err(serde::de::Error::custom),
+    None => Ok(None),
+  }
+}
+
+#[derive(Default, Deserialize)]
 pub struct StorageConf {
+  /// 存储服务，未设置时使用顶层的 `service`
+  pub service: Option<StorageSource>,
+  #[serde(default)]
   pub endpoint: String,
+  /// bucket，Azure Blob 为 container
+  #[serde(default)]
   pub bucket: String,
+  /// access key，Azure Blob 为 account name
+  #[serde(default)]
   pub ak: String,
+  /// secret key，Azure Blob 为 account key，GCS 为 base64 编码的服务账号凭证
+  #[serde(default)]
   pub sk: String,
+  /// 操作的根目录，`fs` 为本地目录
+  pub root: Option<String>,
+  /// S3 的 region
+  pub region: Option<String>,
+  /// S3 是否使用 virtual host style 访问，默认为 path style，MinIO 等兼容存储通常使用 path style
+  #[serde(default)]
+  pub virtual_host_style: bool,
+  /// GCS 服务账号凭证文件路径
+  pub credential_path: Option<String>,
+  /// STS 临时凭证的 security token
+  pub security_token: Option<String>,
+  /// 凭证文件路径，TOML 格式，包含 `ak`、`sk` 与可选的 `security_token`，仅当前用户可读写。ak 未设置时使用
+  pub credential_file: Option<String>,
+  /// 获取凭证的外部命令，标准输出为包含 `ak`、`sk` 与可选的 `security_token` 的 JSON。ak 未设置时使用，
+  /// 优先于 credential_file
+  pub credential_process: Option<String>,
+  /// 已解析的凭证，credential_process 仅执行一次
+  #[serde(skip)]
+  credentials: OnceLock<Credentials>,
+}
+
+impl StorageConf {
+  /// 获取访问凭证：ak 已设置（配置文件、环境变量或命令行）时直接使用，否则依次尝试 credential_process
+  /// 与 credential_file，均未设置时返回空凭证
+  pub fn credentials(&self) -> Result<&Credentials> {
+    if let Some(credentials) = self.credentials.get() {
+      return Ok(credentials);
+    }
+    let credentials = if !self.ak.is_empty() {
+      Credentials { ak: self.ak.clone(), sk: self.sk.clone(), security_token: self.security_token.clone() }
+    } else if let Some(command) = self.credential_process.as_deref() {
+      Credentials::from_process(command)?
+    } else if let Some(file) = self.credential_file.as_deref() {
+      Credentials::from_file(file)?
+    } else {
+      Credentials { security_token: self.security_token.clone(), ..Default::default() }
+    };
+    Ok(self.credentials.get_or_init(|| credentials))
+  }
+
+  /// 是否从 credential_process 或 credential_file 获取凭证
+  fn has_credential_provider(&self) -> bool {
+    self.ak.is_empty() && (self.credential_process.is_some() || self.credential_file.is_some())
+  }
+
+  fn validate(&self, section: &str, service: &StorageSource, problems: &mut Vec<String>) {
+    let required: &[&str] = match service {
+      StorageSource::Obs | StorageSource::Oss => &["endpoint", "bucket", "ak", "sk"],
+      #[cfg(feature = "services-s3")]
+      StorageSource::S3 => &["bucket", "ak", "sk"],
+      #[cfg(feature = "services-cos")]
+      StorageSource::Cos => &["endpoint", "bucket", "ak", "sk"],
+      #[cfg(feature = "services-azblob")]
+      StorageSource::Azblob => &["endpoint", "bucket", "ak", "sk"],
+      #[cfg(feature = "services-gcs")]
+      StorageSource::Gcs => &["bucket"],
+      #[cfg(feature = "services-fs")]
+      StorageSource::Fs => &["root"],
+      #[cfg(feature = "services-memory")]
+      StorageSource::Memory => &[],
+    };
+    let root = self.root.clone().unwrap_or_default();
+    let provided = self.has_credential_provider();
+    for key in required.iter().filter(|key| !(provided && matches!(**key, "ak" | "sk"))) {
+      let value = match *key {
+        "endpoint" => &self.endpoint,
+        "bucket" => &self.bucket,
+        "ak" => &self.ak,
+        "sk" => &self.sk,
+        _ => &root,
+      };
+      if value.trim().is_empty() {
+        problems.push(format!("{}.{}: is empty", section, key));
+      } else if value.starts_with('<') && value.ends_with('>') {
+        problems.push(format!("{}.{}: is a template placeholder", section, key));
+      }
+    }
+    if !self.endpoint.is_empty()
+      && let Err(e) = validate_endpoint(&self.endpoint)
+    {
+      problems.push(format!("{}.endpoint: {}", section, e));
+    }
+    if !service.supports_security_token() && self.security_token.is_some() {
+      problems.push(format!("{}.security_token: is not supported by {}", section, service));
+    } else if self.security_token.is_some()
+      && let Err(e) = check_security_token_env(service, self.security_token.as_deref())
+    {
+      problems.push(format!("{}.security_token: {}", section, e));
+    }
+    if provided
+      && self.credential_process.is_none()
+      && let Some(file) = self.credential_file.as_deref()
+      && let Err(e) = check_file_permission(&expand_home(file))
+    {
+      problems.push(format!("{}.credential_file: {}", section, e));
+    }
+  }
+}
+
+impl std::fmt::Debug for StorageConf {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("StorageConf")
+      .field("service", &self.service)
+      .field("endpoint", &self.endpoint)
+      .field("bucket", &self.bucket)
+      .field("ak", &mask_secret(&self.ak))
+      .field("sk", &mask_secret(&self.sk))
+      .field("root", &self.root)
+      .field("region", &self.region)
+      .field("virtual_host_style", &self.virtual_host_style)
+      .field("credential_path", &self.credential_path)
+      .field("security_token", &self.security_token.as_deref().map(mask_secret))
+      .field("credential_file", &self.credential_file)
+      .field("credential_process", &self.credential_process)
+      .finish()
+  }
+}
+
+/// 检查 endpoint 格式：`[http(s)://]host[:port][/]`
+fn validate_endpoint(endpoint: &str) -> std::result::Result<(), String> {
+  let rest = endpoint.strip_prefix("https://").or_else(|| endpoint.strip_prefix("http://")).unwrap_or(endpoint);
+  let rest = rest.strip_suffix('/').unwrap_or(rest);
+  let (host, port) = match rest.rsplit_once(':') {
+    Some((host, port)) => (host, Some(port)),
+    None => (rest, None),
+  };
+  if host.is_empty() || !host.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-') {
+    return Err(format!("'{}' is not a valid host", host));
+  }
+  if host.starts_with(['.', '-']) || host.ends_with(['.', '-']) {
+    return Err(format!("'{}' is not a valid host", host));
+  }
+  if let Some(port) = port
+    && port.parse::<u16>().is_err()
+  {
+    return Err(format!("'{}' is not a valid port", port));
+  }
+  Ok(())
 }
 
 impl DevopsConf {
   pub fn from_devops_cmd(cmd: &DevopsCmd) -> Result<Self> {
+    let (c, config_files) = Self::merged_config(cmd)?;
+    let mut conf = Self::from_config(c)?;
+    conf.config_files = config_files;
+    Ok(conf)
+  }
+
+  /// 反序列化合并后的配置，未设置 service 时返回错误
+  fn from_config(c: config::Config) -> Result<Self> {
+    match c.get_string("service") {
+      Ok(service) if !service.trim().is_empty() => Ok(c.try_deserialize()?),
+      _ => bail!("The storage service is not set, use '--service', env 'SERVICE' or 'service' in the config file"),
+    }
+  }
+
+  /// 合并 default.toml、配置文件、profile、环境变量与命令行参数，返回合并后的配置及已加载的配置文件。
+  ///
+  /// 优先级从低到高依次为：default.toml < 配置文件 < profile < 环境变量 < 命令行参数
+  pub fn merged_config(cmd: &DevopsCmd) -> Result<(config::Config, Vec<PathBuf>)> {
     let mut cb =
       config::Config::builder().add_source(config::File::from_str(include_str!("default.toml"), FileFormat::Toml));
-    if let Some(config_file) = cmd.config_file.as_deref() {
-      cb = cb.add_source(config::File::with_name(config_file));
+    let config_files = match cmd.config_file.as_deref() {
+      Some(config_file) => {
+        cb = cb.add_source(config::File::with_name(config_file));
+        vec![PathBuf::from(config_file)]
+      }
+      None => {
+        let files = Self::default_config_files();
+        for file in files.iter() {
+          cb = cb.add_source(config::File::from(file.as_path()));
+        }
+        files
+      }
+    };
+    if let Some(profile) = cmd.profile.clone().or_else(|| std::env::var("DEVOPS_PROFILE").ok()) {
+      cb = Self::with_profile(cb, &profile)?;
     }
 
-    if std::env::var("SERVICE").iter().any(|s| s.is_empty()) {
-      // 当环境变量 SERVICE 未设置时
-      if let Some(value) = cmd.service.as_ref() {
-        // 当命令行参数 service 设置时
-        set_env("SERVICE", value.to_string());
-      }
+    // 命令行参数在环境变量之后添加，优先级最高
+    let c = cb.add_source(env_source()).add_source(CliSource::from_devops_cmd(cmd)).build()?;
+    Ok((c, config_files))
+  }
+
+  /// 检查配置的必填项与 endpoint 格式，不发起网络请求，返回发现的问题
+  pub fn validate(&self) -> Vec<String> {
+    let mut problems = Vec::new();
+    match self.storage.as_ref() {
+      Some(sc) => sc.validate("storage", self.service_of(sc), &mut problems),
+      None => problems.push("storage: the [storage] section is missing".to_string()),
     }
-    let service = match std::env::var("SERVICE") {
-      Ok(service) if !service.is_empty() => service,
-      _ => cmd.service.clone().unwrap().to_string(),
-    };
-    set_env("SERVICE", service);
-    if let Some(ak) = cmd.ak.as_deref() {
-      set_env("STORAGE__AK", ak);
+    let mut names = self.storages.keys().collect::<Vec<_>>();
+    names.sort();
+    for name in names {
+      let sc = &self.storages[name];
+      sc.validate(&format!("storages.{}", name), self.service_of(sc), &mut problems);
     }
-    if let Some(sk) = cmd.sk.as_deref() {
-      set_env("STORAGE__SK", sk);
+    problems
+  }
+
+  /// 未通过 `--config-file` 指定配置文件时，自动查找并加载以下存在的配置文件，后者优先级更高：
+  ///
+  /// 1. `~/.config/devops-cli/config.toml`
+  /// 2. `$XDG_CONFIG_HOME/devops-cli/config.toml`
+  /// 3. `./.devops-cli.toml`
+  pub fn default_config_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if let Some(home) = std::env::var_os("HOME").filter(|v| !v.is_empty()) {
+      files.push(PathBuf::from(home).join(".config").join("devops-cli").join("config.toml"));
     }
-    if let Some(bucket) = cmd.bucket.as_deref() {
-      set_env("STORAGE__BUCKET", bucket);
+    if let Some(xdg) = std::env::var_os("XDG_CONFIG_HOME").filter(|v| !v.is_empty()) {
+      let file = PathBuf::from(xdg).join("devops-cli").join("config.toml");
+      if !files.contains(&file) {
+        files.push(file);
+      }
     }
-    Self::from_config_builder(cb)
+    files.push(PathBuf::from(".devops-cli.toml"));
+    files.into_iter().filter(|f| f.is_file()).collect()
+  }
+
+  /// 使用 `[profiles.<name>]` 中的配置覆盖顶层配置，环境变量与命令行参数仍可再覆盖 profile 的配置
+  pub fn with_profile(cb: ConfigBuilder<DefaultState>, profile: &str) -> Result<ConfigBuilder<DefaultState>> {
+    let table = cb
+      .build_cloned()?
+      .get_table(&format!("profiles.{}", profile))
+      .map_err(|_| anyhow!("The profile '{}' is not found", profile))?;
+    Ok(cb.add_source(ProfileSource(table)))
   }
 
   pub fn from_config_builder(cb: ConfigBuilder<DefaultState>) -> Result<Self> {
-    let v = cb.add_source(config::Environment::default().separator("__")).build()?.try_deserialize()?;
-    Ok(v)
+    Self::from_config(cb.add_source(env_source()).build()?)
   }
 
   pub fn from_file(file: &str) -> Result<Self> {
@@ -73,6 +356,59 @@ impl DevopsConf {
   }
 }
 
+/// 环境变量配置源，`__` 分隔嵌套的配置项，如 `STORAGE__AK` 对应 `storage.ak`，忽略值为空的环境变量
+fn env_source() -> config::Environment {
+  config::Environment::default().separator("__").ignore_empty(true)
+}
+
+/// 命令行参数配置源，仅包含命令行中指定的参数
+#[derive(Debug, Clone, Default)]
+struct CliSource(Map<String, Value>);
+
+impl CliSource {
+  fn from_devops_cmd(cmd: &DevopsCmd) -> Self {
+    let mut source = Self::default();
+    source.set("service", cmd.service.as_ref().map(ToString::to_string));
+    source.set("storage.ak", cmd.ak.clone());
+    source.set("storage.sk", cmd.sk.clone());
+    source.set("storage.bucket", cmd.bucket.clone());
+    source.set("transfer.max_retries", cmd.retries.map(|v| v as u64));
+    source.set("transfer.timeout", cmd.timeout);
+    source.set("transfer.bandwidth", cmd.bandwidth.map(|v| v as u64));
+    source
+  }
+
+  fn set<V: Into<ValueKind>>(&mut self, key: &str, value: Option<V>) {
+    if let Some(value) = value {
+      self.0.insert(key.to_string(), Value::new(Some(&"command line".to_string()), value));
+    }
+  }
+}
+
+impl Source for CliSource {
+  fn clone_into_box(&self) -> Box<dyn Source + Send + Sync> {
+    Box::new(self.clone())
+  }
+
+  fn collect(&self) -> Result<Map<String, Value>, ConfigError> {
+    Ok(self.0.clone())
+  }
+}
+
+/// 以选中的 profile 表作为配置源
+#[derive(Debug, Clone)]
+struct ProfileSource(Map<String, Value>);
+
+impl Source for ProfileSource {
+  fn clone_into_box(&self) -> Box<dyn Source + Send + Sync> {
+    Box::new(self.clone())
+  }
+
+  fn collect(&self) -> Result<Map<String, Value>, ConfigError> {
+    Ok(self.0.clone())
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use std::path::Path;
@@ -107,4 +443,162 @@ mod tests {
 
     Ok(())
   }
+
+  #[test]
+  fn test_devops_conf_profile() -> Result<()> {
+    let toml = r#"
+service = "obs"
+
+[storage]
+endpoint = "obs.cn-southwest-2.myhuaweicloud.com"
+bucket = "dev-bucket"
+ak = "<ak>"
+sk = "<sk>"
+
+[profiles.prod.storage]
+bucket = "prod-bucket"
+"#;
+    let cb = config::Config::builder().add_source(config::File::from_str(toml, FileFormat::Toml));
+    let conf = DevopsConf::from_config_builder(DevopsConf::with_profile(cb.clone(), "prod")?)?;
+    let sc = conf.storage().unwrap();
+    assert_eq!(sc.bucket, "prod-bucket");
+    assert_eq!(sc.endpoint, "obs.cn-southwest-2.myhuaweicloud.com");
+
+    assert!(DevopsConf::with_profile(cb, "staging").is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn test_devops_conf_cli_source() -> Result<()> {
+    let file = std::env::var("CARGO_MANIFEST_DIR")? + "/examples/app-template.toml";
+    let cmd = DevopsCmd {
+      service: Some(StorageSource::Oss),
+      bucket: Some("cli-bucket".to_string()),
+      timeout: Some(5),
+      config_file: Some(file),
+      ..Default::default()
+    };
+    let (c, _) = DevopsConf::merged_config(&cmd)?;
+    let storage = c.collect()?.remove("storage").unwrap().into_table()?;
+    assert_eq!(storage["bucket"].origin(), Some("command line"));
+
+    let conf = DevopsConf::from_devops_cmd(&cmd)?;
+    assert_eq!(conf.service(), &StorageSource::Oss);
+    assert_eq!(conf.storage().unwrap().bucket, "cli-bucket");
+    assert_eq!(conf.storage().unwrap().endpoint, "obs.cn-southwest-2.myhuaweicloud.com");
+    assert_eq!(conf.transfer().timeout, 5);
+    Ok(())
+  }
+
+  #[test]
+  fn test_devops_conf_missing_service() {
+    let toml = "[storage]\nroot = \"/tmp\"";
+    let cb = config::Config::builder().add_source(config::File::from_str(toml, FileFormat::Toml));
+    let err = DevopsConf::from_config_builder(cb).unwrap_err();
+    assert!(err.to_string().contains("The storage service is not set"));
+  }
+
+  #[test]
+  fn test_transfer_conf() -> Result<()> {
+    let toml = r#"
+service = "obs"
+
+[transfer]
+max_retries = 5
+bandwidth = "10MiB"
+"#;
+    let cb = config::Config::builder().add_source(config::File::from_str(toml, FileFormat::Toml));
+    let conf: DevopsConf = cb.build()?.try_deserialize()?;
+    assert_eq!(conf.transfer().max_retries, 5);
+    assert_eq!(conf.transfer().timeout, 60);
+    assert_eq!(conf.transfer().bandwidth, Some(10 * 1024 * 1024));
+    Ok(())
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_storage_conf_credentials() -> Result<()> {
+    let toml = r#"
+service = "obs"
+
+[storage]
+endpoint = "obs.cn-southwest-2.myhuaweicloud.com"
+bucket = "<bucket>"
+credential_process = "echo '{\"ak\": \"<process-ak>\", \"sk\": \"<process-sk>\", \"security_token\": \"<token>\"}'"
+
+[profiles.inline.storage]
+ak = "<ak>"
+sk = "<sk>"
+"#;
+    let cb = config::Config::builder().add_source(config::File::from_str(toml, FileFormat::Toml));
+    let conf: DevopsConf = cb.build_cloned()?.try_deserialize()?;
+    let credentials = conf.storage().unwrap().credentials()?;
+    assert_eq!(credentials.ak, "<process-ak>");
+    assert_eq!(credentials.security_token.as_deref(), Some("<token>"));
+    assert!(conf.validate().iter().all(|p| !p.starts_with("storage.ak") && !p.starts_with("storage.sk")));
+
+    let conf: DevopsConf = DevopsConf::with_profile(cb, "inline")?.build()?.try_deserialize()?;
+    let credentials = conf.storage().unwrap().credentials()?;
+    assert_eq!(credentials.ak, "<ak>");
+    assert_eq!(credentials.security_token, None);
+    Ok(())
+  }
+
+  #[test]
+  fn test_validate_endpoint() {
+    assert!(validate_endpoint("obs.cn-southwest-2.myhuaweicloud.com").is_ok());
+    assert!(validate_endpoint("https://oss-cn-hangzhou.aliyuncs.com/").is_ok());
+    assert!(validate_endpoint("http://127.0.0.1:9000").is_ok());
+    assert!(validate_endpoint("").is_err());
+    assert!(validate_endpoint("obs.example.com/bucket").is_err());
+    assert!(validate_endpoint("obs.example.com:port").is_err());
+  }
+
+  #[test]
+  fn test_validate_named_security_token() -> Result<()> {
+    let toml = r#"
+service = "obs"
+
+[storage]
+endpoint = "obs.cn-southwest-2.myhuaweicloud.com"
+bucket = "demo"
+ak = "ak"
+sk = "sk"
+
+[storages.backup]
+service = "oss"
+endpoint = "oss-cn-hangzhou.aliyuncs.com"
+bucket = "backup"
+ak = "ak"
+sk = "sk"
+security_token = "token"
+"#;
+    let cb = config::Config::builder().add_source(config::File::from_str(toml, FileFormat::Toml));
+    let conf: DevopsConf = cb.build()?.try_deserialize()?;
+    // 命名存储的 STS token 同样须预先导出到环境变量，validate 中提前报告
+    if std::env::var_os("ALIBABA_CLOUD_SECURITY_TOKEN").is_none() {
+      let problems = conf.validate();
+      assert!(problems.iter().any(|p| p.starts_with("storages.backup.security_token")), "{:?}", problems);
+    }
+    Ok(())
+  }
+
+  #[cfg(feature = "services-fs")]
+  #[test]
+  fn test_storage_service() -> Result<()> {
+    let toml = r#"
+service = "obs"
+
+[storage]
+service = "fs"
+root = "/tmp/devops-cli"
+"#;
+    let cb = config::Config::builder().add_source(config::File::from_str(toml, FileFormat::Toml));
+    let conf: DevopsConf = cb.build()?.try_deserialize()?;
+    let sc = conf.storage().unwrap();
+    assert_eq!(conf.service_of(sc), &StorageSource::Fs);
+    // 按 fs 检查，不要求 obs 的 endpoint、bucket 与 ak/sk
+    assert!(conf.validate().is_empty(), "{:?}", conf.validate());
+    Ok(())
+  }
 }
@@ -0,0 +1,132 @@
+use std::{
+  path::{Path, PathBuf},
+  process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result, bail};
+use config::FileFormat;
+use serde::Deserialize;
+
+use crate::utils::mask_secret;
+
+/// 访问凭证，security_token 为 STS 临时凭证的 token
+#[derive(Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Credentials {
+  #[serde(alias = "AccessKeyId")]
+  pub ak: String,
+  #[serde(alias = "SecretAccessKey")]
+  pub sk: String,
+  #[serde(default, alias = "SessionToken", alias = "SecurityToken")]
+  pub security_token: Option<String>,
+}
+
+impl std::fmt::Debug for Credentials {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Credentials")
+      .field("ak", &mask_secret(&self.ak))
+      .field("sk", &mask_secret(&self.sk))
+      .field("security_token", &self.security_token.as_deref().map(mask_secret))
+      .finish()
+  }
+}
+
+impl Credentials {
+  /// 从 TOML 格式的凭证文件读取，文件不能被其他用户访问
+  pub fn from_file(path: &str) -> Result<Self> {
+    let path = expand_home(path);
+    check_file_permission(&path)?;
+    let credentials = config::Config::builder()
+      .add_source(config::File::from(path.as_path()).format(FileFormat::Toml))
+      .build()?
+      .try_deserialize()
+      .with_context(|| format!("Invalid credential file '{}'", path.display()))?;
+    Ok(credentials)
+  }
+
+  /// 执行外部命令，从其标准输出的 JSON 中读取凭证。JSON 字段为 `ak`、`sk`、`security_token`，
+  /// 也支持 `AccessKeyId`、`SecretAccessKey`、`SessionToken`
+  pub fn from_process(command: &str) -> Result<Self> {
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let output = Command::new(shell)
+      .arg(flag)
+      .arg(command)
+      .stdin(Stdio::null())
+      .stderr(Stdio::inherit())
+      .output()
+      .with_context(|| format!("Failed to execute credential_process '{}'", command))?;
+    if !output.status.success() {
+      bail!("The credential_process '{}' exited with {}", command, output.status);
+    }
+    serde_json::from_slice(&output.stdout)
+      .with_context(|| format!("The output of credential_process '{}' is not valid credentials json", command))
+  }
+}
+
+/// 检查凭证文件是否存在且仅当前用户可读写（Unix 下权限不能包含 group 与 other）
+pub fn check_file_permission(path: &Path) -> Result<()> {
+  let md = std::fs::metadata(path).with_context(|| format!("The credential file '{}' is not found", path.display()))?;
+  if !md.is_file() {
+    bail!("The credential file '{}' is not a file", path.display());
+  }
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = md.permissions().mode() & 0o777;
+    if mode & 0o077 != 0 {
+      bail!(
+        "The credential file '{}' is accessible by other users (mode {:o}), please run `chmod 600 {}`",
+        path.display(),
+        mode,
+        path.display()
+      );
+    }
+  }
+  Ok(())
+}
+
+/// 展开路径开头的 `~/` 为 HOME 目录
+pub fn expand_home(path: &str) -> PathBuf {
+  match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+    (Some(rest), Some(home)) if !home.is_empty() => PathBuf::from(home).join(rest),
+    _ => PathBuf::from(path),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[cfg(unix)]
+  #[test]
+  fn test_credentials_from_file() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = std::env::temp_dir().join(format!("storage-cli-credentials-{}.toml", std::process::id()));
+    std::fs::write(&path, "ak = \"<ak>\"\nsk = \"<sk>\"\nsecurity_token = \"<token>\"\n")?;
+    let file = path.to_string_lossy().to_string();
+
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644))?;
+    assert!(Credentials::from_file(&file).is_err());
+
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    let credentials = Credentials::from_file(&file)?;
+    std::fs::remove_file(&path)?;
+    assert_eq!(credentials.ak, "<ak>");
+    assert_eq!(credentials.sk, "<sk>");
+    assert_eq!(credentials.security_token.as_deref(), Some("<token>"));
+    Ok(())
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_credentials_from_process() -> Result<()> {
+    let credentials = Credentials::from_process(r#"echo '{"AccessKeyId": "<ak>", "SecretAccessKey": "<sk>"}'"#)?;
+    assert_eq!(credentials.ak, "<ak>");
+    assert_eq!(credentials.sk, "<sk>");
+    assert_eq!(credentials.security_token, None);
+    assert!(Credentials::from_process("exit 1").is_err());
+    assert!(Credentials::from_process("echo not-json").is_err());
+    Ok(())
+  }
+}
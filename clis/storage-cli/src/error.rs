@@ -0,0 +1,91 @@
+use std::path::Path;
+
+/// [`StorageClient`](crate::client::StorageClient) 返回的错误
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+  /// 配置无效，如缺少存储配置、凭证获取失败
+  #[error("Invalid storage config: {0:#}")]
+  Config(anyhow::Error),
+  /// 对象或本地文件不存在
+  #[error("'{0}' is not found")]
+  NotFound(String),
+  /// 本地文件已存在
+  #[error("The file '{0}' already exists")]
+  AlreadyExists(String),
+  /// 传输内容的校验和与对象不一致
+  #[error("Checksum mismatch of '{path}': the {algorithm} of the object is {remote}, but {local} is transferred")]
+  ChecksumMismatch { path: String, algorithm: &'static str, remote: String, local: String },
+  /// 存储服务返回的错误
+  #[error(transparent)]
+  Storage(Box<opendal::Error>),
+  #[error(transparent)]
+  Io(#[from] std::io::Error),
+  #[error(transparent)]
+  Other(anyhow::Error),
+}
+
+impl StorageError {
+  /// 将不存在、已存在的错误转换为包含路径的错误，对象存储的错误使用 object_key，本地文件的错误使用 path
+  pub(crate) fn with_path(self, object_key: &str, path: &Path) -> Self {
+    match self {
+      StorageError::Storage(e) if e.kind() == opendal::ErrorKind::NotFound => {
+        StorageError::NotFound(object_key.to_string())
+      }
+      StorageError::Io(e) if e.kind() == std::io::ErrorKind::NotFound => {
+        StorageError::NotFound(path.display().to_string())
+      }
+      StorageError::Io(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+        StorageError::AlreadyExists(path.display().to_string())
+      }
+      e => e,
+    }
+  }
+}
+
+impl From<opendal::Error> for StorageError {
+  fn from(err: opendal::Error) -> Self {
+    StorageError::Storage(Box::new(err))
+  }
+}
+
+/// 内部函数返回 `anyhow::Error`，按原始错误类型还原
+impl From<anyhow::Error> for StorageError {
+  fn from(err: anyhow::Error) -> Self {
+    let err = match err.downcast::<StorageError>() {
+      Ok(e) => return e,
+      Err(err) => err,
+    };
+    let err = match err.downcast::<opendal::Error>() {
+      Ok(e) => return e.into(),
+      Err(err) => err,
+    };
+    match err.downcast::<std::io::Error>() {
+      Ok(e) => StorageError::Io(e),
+      Err(err) => StorageError::Other(err),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_storage_error_from_anyhow() {
+    let err = StorageError::from(anyhow::Error::from(opendal::Error::new(opendal::ErrorKind::NotFound, "not found")));
+    assert!(matches!(err.with_path("demo.txt", Path::new("demo.txt")), StorageError::NotFound(p) if p == "demo.txt"));
+
+    let err = std::io::Error::from(std::io::ErrorKind::AlreadyExists);
+    let err = StorageError::from(anyhow::Error::from(err)).with_path("demo.txt", Path::new("/tmp/demo.txt"));
+    assert!(matches!(err, StorageError::AlreadyExists(p) if p == "/tmp/demo.txt"));
+
+    let err = StorageError::ChecksumMismatch {
+      path: "demo.txt".to_string(),
+      algorithm: "md5",
+      remote: "a".to_string(),
+      local: "b".to_string(),
+    };
+    assert!(matches!(StorageError::from(anyhow::Error::from(err)), StorageError::ChecksumMismatch { .. }));
+    assert!(matches!(StorageError::from(anyhow::anyhow!("other")), StorageError::Other(_)));
+  }
+}
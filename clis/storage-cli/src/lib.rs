@@ -1,4 +1,7 @@
+pub mod client;
 pub mod cmd;
 pub mod conf;
+pub mod credentials;
+pub mod error;
 pub mod operators;
 pub mod utils;
@@ -1,32 +1,415 @@
-use anyhow::{Result, anyhow};
+use std::{
+  sync::{Arc, Mutex},
+  time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow, bail};
+use log::warn;
 use opendal::{
-  Operator,
+  Buffer, Operator,
+  layers::{RetryInterceptor, RetryLayer, TimeoutLayer},
+  raw::{Access, Layer, LayeredAccess, OpList, OpRead, OpWrite, RpList, RpRead, RpWrite, oio},
   services::{Obs, Oss},
 };
 
 use crate::{
   cmd::StorageSource,
-  conf::{DevopsConf, StorageConf},
+  conf::{DevopsConf, StorageConf, TransferConf},
 };
 
 pub async fn get_operator(cc: &DevopsConf) -> Result<Operator> {
   let sc = cc.storage().ok_or_else(|| anyhow!("The storage config is not found"))?;
-  match cc.service() {
+  Ok(with_transfer_layers(build_operator(cc.service_of(sc), sc)?, cc.transfer()))
+}
+
+/// 根据 `[storages.<name>]` 命名存储配置构建 Operator
+pub async fn get_named_operator(cc: &DevopsConf, name: &str) -> Result<Operator> {
+  let sc = cc.named_storage(name).ok_or_else(|| anyhow!("The storage '{}' is not found", name))?;
+  let op = build_operator(cc.service_of(sc), sc)
+    .map_err(|e| anyhow!("The storage '{}' is invalid: {}", name, e))?;
+  Ok(with_transfer_layers(op, cc.transfer()))
+}
+
+/// 按 `[transfer]` 配置添加超时、重试与带宽限制。超时在重试之内，超时的请求会被重试；带宽限制在最外层，
+/// 等待的时间不计入超时
+pub fn with_transfer_layers(op: Operator, tc: &TransferConf) -> Operator {
+  let timeout = TimeoutLayer::new()
+    .with_timeout(Duration::from_secs(tc.timeout))
+    .with_io_timeout(Duration::from_secs(tc.io_timeout));
+  let op = op
+    .layer(timeout)
+    .layer(
+      RetryLayer::new()
+        .with_max_times(tc.max_retries)
+        .with_min_delay(Duration::from_secs_f64(tc.retry_min_delay))
+        .with_max_delay(Duration::from_secs_f64(tc.retry_max_delay))
+        .with_jitter()
+        .with_notify(RetryLogger),
+    );
+  match tc.bandwidth {
+    Some(bandwidth) if bandwidth > 0 => op.layer(BandwidthLayer::new(bandwidth as u64)),
+    _ => op,
+  }
+}
+
+/// 记录每次重试的原因与等待时间
+struct RetryLogger;
+
+impl RetryInterceptor for RetryLogger {
+  fn intercept(&self, err: &opendal::Error, dur: Duration) {
+    warn!("Request failed, retry after {:.1}s: {}", dur.as_secs_f64(), err);
+  }
+}
+
+/// 限制读写带宽的 Layer，同一 Operator 的所有读写共享带宽。
+///
+/// opendal 自带的 `ThrottleLayer` 仅限制写入，且超出限制时忙等待，因此在读写完成后按已传输的字节数休眠，
+/// 使平均速率不超过 bandwidth
+#[derive(Clone)]
+struct BandwidthLayer {
+  limiter: Arc<BandwidthLimiter>,
+}
+
+impl BandwidthLayer {
+  fn new(bytes_per_sec: u64) -> Self {
+    Self { limiter: Arc::new(BandwidthLimiter::new(bytes_per_sec)) }
+  }
+}
+
+impl<A: Access> Layer<A> for BandwidthLayer {
+  type LayeredAccess = BandwidthAccessor<A>;
+
+  fn layer(&self, inner: A) -> Self::LayeredAccess {
+    BandwidthAccessor { inner, limiter: self.limiter.clone() }
+  }
+}
+
+#[derive(Debug)]
+pub(crate) struct BandwidthLimiter {
+  bytes_per_sec: u64,
+  /// 按带宽计算的下一次可以传输的时间
+  next: Mutex<Instant>,
+}
+
+impl BandwidthLimiter {
+  pub(crate) fn new(bytes_per_sec: u64) -> Self {
+    Self { bytes_per_sec, next: Mutex::new(Instant::now()) }
+  }
+
+  /// 记录已传输的字节数，超出带宽时休眠
+  pub(crate) async fn consume(&self, n: usize) {
+    let wait = {
+      let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+      let now = Instant::now();
+      *next = (*next).max(now) + Duration::from_secs_f64(n as f64 / self.bytes_per_sec as f64);
+      next.saturating_duration_since(now)
+    };
+    if !wait.is_zero() {
+      tokio::time::sleep(wait).await;
+    }
+  }
+}
+
+#[derive(Debug)]
+struct BandwidthAccessor<A: Access> {
+  inner: A,
+  limiter: Arc<BandwidthLimiter>,
+}
+
+impl<A: Access> LayeredAccess for BandwidthAccessor<A> {
+  type Inner = A;
+  type Reader = BandwidthWrapper<A::Reader>;
+  type BlockingReader = A::BlockingReader;
+  type Writer = BandwidthWrapper<A::Writer>;
+  type BlockingWriter = A::BlockingWriter;
+  type Lister = A::Lister;
+  type BlockingLister = A::BlockingLister;
+
+  fn inner(&self) -> &Self::Inner {
+    &self.inner
+  }
+
+  async fn read(&self, path: &str, args: OpRead) -> opendal::Result<(RpRead, Self::Reader)> {
+    let (rp, r) = self.inner.read(path, args).await?;
+    Ok((rp, BandwidthWrapper { inner: r, limiter: self.limiter.clone() }))
+  }
+
+  async fn write(&self, path: &str, args: OpWrite) -> opendal::Result<(RpWrite, Self::Writer)> {
+    let (rp, w) = self.inner.write(path, args).await?;
+    Ok((rp, BandwidthWrapper { inner: w, limiter: self.limiter.clone() }))
+  }
+
+  async fn list(&self, path: &str, args: OpList) -> opendal::Result<(RpList, Self::Lister)> {
+    self.inner.list(path, args).await
+  }
+
+  fn blocking_read(&self, path: &str, args: OpRead) -> opendal::Result<(RpRead, Self::BlockingReader)> {
+    self.inner.blocking_read(path, args)
+  }
+
+  fn blocking_write(&self, path: &str, args: OpWrite) -> opendal::Result<(RpWrite, Self::BlockingWriter)> {
+    self.inner.blocking_write(path, args)
+  }
+
+  fn blocking_list(&self, path: &str, args: OpList) -> opendal::Result<(RpList, Self::BlockingLister)> {
+    self.inner.blocking_list(path, args)
+  }
+}
+
+struct BandwidthWrapper<R> {
+  inner: R,
+  limiter: Arc<BandwidthLimiter>,
+}
+
+impl<R: oio::Read> oio::Read for BandwidthWrapper<R> {
+  async fn read(&mut self) -> opendal::Result<Buffer> {
+    let bs = self.inner.read().await?;
+    self.limiter.consume(bs.len()).await;
+    Ok(bs)
+  }
+}
+
+impl<R: oio::Write> oio::Write for BandwidthWrapper<R> {
+  async fn write(&mut self, bs: Buffer) -> opendal::Result<usize> {
+    let n = self.inner.write(bs).await?;
+    self.limiter.consume(n).await;
+    Ok(n)
+  }
+
+  async fn close(&mut self) -> opendal::Result<()> {
+    self.inner.close().await
+  }
+
+  async fn abort(&mut self) -> opendal::Result<()> {
+    self.inner.abort().await
+  }
+}
+
+/// 根据存储服务与存储配置构建 Operator，不添加 `[transfer]` 的 Layer
+pub fn build_operator(service: &StorageSource, sc: &StorageConf) -> Result<Operator> {
+  match service {
     StorageSource::Obs => builder_obs(sc),
     StorageSource::Oss => builder_oss(sc),
+    #[cfg(feature = "services-s3")]
+    StorageSource::S3 => builder_s3(sc),
+    #[cfg(feature = "services-cos")]
+    StorageSource::Cos => builder_cos(sc),
+    #[cfg(feature = "services-azblob")]
+    StorageSource::Azblob => builder_azblob(sc),
+    #[cfg(feature = "services-gcs")]
+    StorageSource::Gcs => builder_gcs(sc),
+    #[cfg(feature = "services-fs")]
+    StorageSource::Fs => builder_fs(sc),
+    #[cfg(feature = "services-memory")]
+    StorageSource::Memory => builder_memory(sc),
+  }
+}
+
+const HUAWEI_CLOUD_SECURITY_TOKEN: &str = "HUAWEI_CLOUD_SECURITY_TOKEN";
+const ALIBABA_CLOUD_SECURITY_TOKEN: &str = "ALIBABA_CLOUD_SECURITY_TOKEN";
+#[cfg(feature = "services-cos")]
+const TENCENTCLOUD_SECURITY_TOKEN: &str = "TENCENTCLOUD_SECURITY_TOKEN";
+/// COS 签名时优先读取该环境变量，其次为 `TENCENTCLOUD_SECURITY_TOKEN`
+#[cfg(feature = "services-cos")]
+const TENCENTCLOUD_TOKEN: &str = "TENCENTCLOUD_TOKEN";
+
+/// opendal 的 OSS、OBS、COS 不支持直接设置 security token，仅在构建时从进程环境变量读取。为避免修改进程的
+/// 环境变量，凭证中的 security token 须由调用方在启动前导出，此处检查两者一致，避免使用过期或其它账号的
+/// token 签名。同一进程中同一存储服务只能使用一个 token，`[storages.<name>]` 与 `[storage]` 同样受此限制
+pub(crate) fn check_security_token_env(service: &StorageSource, token: Option<&str>) -> Result<()> {
+  // overrides 为 opendal 优先于 var 读取的环境变量
+  let (var, overrides): (&str, &[&str]) = match service {
+    StorageSource::Obs => (HUAWEI_CLOUD_SECURITY_TOKEN, &[]),
+    StorageSource::Oss => (ALIBABA_CLOUD_SECURITY_TOKEN, &[]),
+    #[cfg(feature = "services-cos")]
+    StorageSource::Cos => (TENCENTCLOUD_SECURITY_TOKEN, &[TENCENTCLOUD_TOKEN]),
+    #[allow(unreachable_patterns)]
+    _ => return Ok(()),
+  };
+  let env = overrides
+    .iter()
+    .chain([&var])
+    .find_map(|v| std::env::var(v).ok().filter(|e| !e.is_empty()).map(|e| (*v, e)));
+  match (token, env) {
+    (Some(token), Some((_, env))) if token == env => Ok(()),
+    (Some(_), Some((used, _))) if used != var => bail!("The env '{}' overrides the security_token, unset it", used),
+    (Some(_), _) => bail!("The security_token is only read from env '{}', export the same token before startup", var),
+    (None, Some((used, _))) => bail!("The env '{}' is set, but the ak/sk is not a STS credential", used),
+    (None, None) => Ok(()),
+  }
+}
+
+/// 存储服务不支持 STS 临时凭证时，设置了 security_token 则返回错误，避免静默忽略后以 ak/sk 签名失败
+#[cfg(any(feature = "services-azblob", feature = "services-gcs"))]
+fn reject_security_token(service: StorageSource, token: Option<&str>) -> Result<()> {
+  if !service.supports_security_token() && token.is_some() {
+    bail!("The security_token is not supported by the storage '{}'", service);
   }
+  Ok(())
 }
 
 fn builder_oss(sc: &StorageConf) -> Result<Operator> {
+  let credentials = sc.credentials()?;
+  check_security_token_env(&StorageSource::Oss, credentials.security_token.as_deref())?;
   let mut b = Oss::default();
-  b.bucket(&sc.bucket).endpoint(&sc.endpoint).access_key_id(&sc.ak).access_key_secret(&sc.sk);
+  b.bucket(&sc.bucket).endpoint(&sc.endpoint).access_key_id(&credentials.ak).access_key_secret(&credentials.sk);
+  if let Some(root) = sc.root.as_deref() {
+    b.root(root);
+  }
   let op = Operator::new(b)?;
   Ok(op.finish())
 }
 
 fn builder_obs(sc: &StorageConf) -> Result<Operator> {
+  let credentials = sc.credentials()?;
+  check_security_token_env(&StorageSource::Obs, credentials.security_token.as_deref())?;
   let mut b: Obs = Obs::default();
-  b.bucket(&sc.bucket).endpoint(&sc.endpoint).access_key_id(&sc.ak).secret_access_key(&sc.sk);
+  b.bucket(&sc.bucket).endpoint(&sc.endpoint).access_key_id(&credentials.ak).secret_access_key(&credentials.sk);
+  if let Some(root) = sc.root.as_deref() {
+    b.root(root);
+  }
+  let op = Operator::new(b)?;
+  Ok(op.finish())
+}
+
+#[cfg(feature = "services-s3")]
+fn builder_s3(sc: &StorageConf) -> Result<Operator> {
+  let credentials = sc.credentials()?;
+  let mut b = opendal::services::S3::default();
+  b.bucket(&sc.bucket).access_key_id(&credentials.ak).secret_access_key(&credentials.sk);
+  if let Some(token) = credentials.security_token.as_deref() {
+    b.security_token(token);
+  }
+  if !sc.endpoint.is_empty() {
+    b.endpoint(&sc.endpoint);
+  }
+  // MinIO 等兼容存储通常不校验 region，未设置时使用 us-east-1
+  b.region(sc.region.as_deref().unwrap_or("us-east-1"));
+  if sc.virtual_host_style {
+    b.enable_virtual_host_style();
+  }
+  if let Some(root) = sc.root.as_deref() {
+    b.root(root);
+  }
+  let op = Operator::new(b)?;
+  Ok(op.finish())
+}
+
+#[cfg(feature = "services-cos")]
+fn builder_cos(sc: &StorageConf) -> Result<Operator> {
+  let credentials = sc.credentials()?;
+  check_security_token_env(&StorageSource::Cos, credentials.security_token.as_deref())?;
+  let mut b = opendal::services::Cos::default();
+  b.bucket(&sc.bucket).endpoint(&sc.endpoint).secret_id(&credentials.ak).secret_key(&credentials.sk);
+  if let Some(root) = sc.root.as_deref() {
+    b.root(root);
+  }
+  let op = Operator::new(b)?;
+  Ok(op.finish())
+}
+
+#[cfg(feature = "services-azblob")]
+fn builder_azblob(sc: &StorageConf) -> Result<Operator> {
+  let credentials = sc.credentials()?;
+  reject_security_token(StorageSource::Azblob, credentials.security_token.as_deref())?;
+  let mut b = opendal::services::Azblob::default();
+  b.container(&sc.bucket).endpoint(&sc.endpoint).account_name(&credentials.ak).account_key(&credentials.sk);
+  if let Some(root) = sc.root.as_deref() {
+    b.root(root);
+  }
+  let op = Operator::new(b)?;
+  Ok(op.finish())
+}
+
+#[cfg(feature = "services-gcs")]
+fn builder_gcs(sc: &StorageConf) -> Result<Operator> {
+  let credentials = sc.credentials()?;
+  reject_security_token(StorageSource::Gcs, credentials.security_token.as_deref())?;
+  let mut b = opendal::services::Gcs::default();
+  b.bucket(&sc.bucket);
+  if !sc.endpoint.is_empty() {
+    b.endpoint(&sc.endpoint);
+  }
+  if let Some(path) = sc.credential_path.as_deref() {
+    b.credential_path(path);
+  } else if !credentials.sk.is_empty() {
+    b.credential(&credentials.sk);
+  }
+  if let Some(root) = sc.root.as_deref() {
+    b.root(root);
+  }
+  let op = Operator::new(b)?;
+  Ok(op.finish())
+}
+
+#[cfg(feature = "services-fs")]
+fn builder_fs(sc: &StorageConf) -> Result<Operator> {
+  let mut b = opendal::services::Fs::default();
+  b.root(sc.root.as_deref().ok_or_else(|| anyhow!("The 'root' of fs storage is not set"))?);
+  let op = Operator::new(b)?;
+  Ok(op.finish())
+}
+
+#[cfg(feature = "services-memory")]
+fn builder_memory(sc: &StorageConf) -> Result<Operator> {
+  let mut b = opendal::services::Memory::default();
+  if let Some(root) = sc.root.as_deref() {
+    b.root(root);
+  }
   let op = Operator::new(b)?;
   Ok(op.finish())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[cfg(feature = "services-memory")]
+  #[tokio::test]
+  async fn test_bandwidth_layer() -> Result<()> {
+    let op = Operator::new(opendal::services::Memory::default())?.finish();
+    let tc = TransferConf { bandwidth: Some(1024 * 1024), ..Default::default() };
+    let op = with_transfer_layers(op, &tc);
+
+    let start = Instant::now();
+    op.write("demo.bin", vec![0_u8; 256 * 1024]).await?;
+    op.read("demo.bin").await?;
+    // 共读写 512KiB，按 1MiB/s 限速至少需要 0.5 秒
+    assert!(start.elapsed() >= Duration::from_millis(450));
+    Ok(())
+  }
+
+  #[cfg(feature = "services-memory")]
+  #[tokio::test]
+  async fn test_storage_service() -> Result<()> {
+    let toml = "service = \"obs\"\n\n[storage]\nservice = \"memory\"\n";
+    let cb = config::Config::builder().add_source(config::File::from_str(toml, config::FileFormat::Toml));
+    let op = get_operator(&DevopsConf::from_config_builder(cb)?).await?;
+    assert_eq!(op.info().scheme(), opendal::Scheme::Memory);
+    Ok(())
+  }
+
+  #[cfg(feature = "services-gcs")]
+  #[test]
+  fn test_reject_security_token() {
+    let mut sc = StorageConf::default();
+    sc.bucket = "demo".into();
+    sc.security_token = Some("token".into());
+    let err = build_operator(&StorageSource::Gcs, &sc).unwrap_err();
+    assert_eq!(err.to_string(), "The security_token is not supported by the storage 'gcs'");
+  }
+
+  #[test]
+  fn test_security_token_env_required() {
+    let mut sc = StorageConf::default();
+    sc.bucket = "demo".into();
+    sc.endpoint = "oss-cn-hangzhou.aliyuncs.com".into();
+    sc.security_token = Some("token".into());
+    // 不修改环境变量，token 未预先导出时报错
+    if std::env::var_os(ALIBABA_CLOUD_SECURITY_TOKEN).is_none() {
+      let err = build_operator(&StorageSource::Oss, &sc).unwrap_err();
+      assert!(err.to_string().contains(ALIBABA_CLOUD_SECURITY_TOKEN));
+      assert!(std::env::var_os(ALIBABA_CLOUD_SECURITY_TOKEN).is_none());
+    }
+  }
+}
@@ -1,11 +1,122 @@
-use std::ffi::OsStr;
-
-pub fn set_env<K, V>(key: K, value: V)
-where
-  K: AsRef<OsStr>,
-  V: AsRef<OsStr>,
-{
-  unsafe {
-    std::env::set_var(key, value);
+use std::{
+  io::{BufRead, Write},
+  time::Duration,
+};
+
+/// 交互式确认，仅当输入 `y` 或 `yes` 时返回 `true`
+pub fn confirm(prompt: &str) -> std::io::Result<bool> {
+  let mut stderr = std::io::stderr();
+  write!(stderr, "{} [y/N] ", prompt)?;
+  stderr.flush()?;
+
+  let mut answer = String::new();
+  std::io::stdin().lock().read_line(&mut answer)?;
+  Ok(matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes"))
+}
+
+/// 掩码敏感信息，仅保留首尾各 2 个字符，过短时全部掩码
+pub fn mask_secret(secret: &str) -> String {
+  let chars = secret.chars().collect::<Vec<_>>();
+  if chars.len() < 12 {
+    return "******".to_string();
+  }
+  format!("{}******{}", chars[..2].iter().collect::<String>(), chars[chars.len() - 2..].iter().collect::<String>())
+}
+
+/// 交互式输入，输入为空时返回默认值
+pub fn prompt(message: &str, default: Option<&str>) -> std::io::Result<String> {
+  let mut stderr = std::io::stderr();
+  match default {
+    Some(default) => write!(stderr, "{} [{}]: ", message, default)?,
+    None => write!(stderr, "{}: ", message)?,
+  }
+  stderr.flush()?;
+
+  let mut answer = String::new();
+  std::io::stdin().lock().read_line(&mut answer)?;
+  let answer = answer.trim();
+  Ok(if answer.is_empty() { default.unwrap_or_default().to_string() } else { answer.to_string() })
+}
+
+/// 解析带单位的字节大小，如 `8388608`、`512K`、`8MiB`、`1G`，单位均按 1024 进制计算
+pub fn parse_size(s: &str) -> Result<usize, String> {
+  let s = s.trim();
+  let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+  let (num, unit) = s.split_at(split);
+  let num = num.parse::<usize>().map_err(|_| format!("invalid size '{}'", s))?;
+  let multiplier = match unit.trim().to_ascii_lowercase().as_str() {
+    "" | "b" => 1,
+    "k" | "kb" | "kib" => 1024,
+    "m" | "mb" | "mib" => 1024 * 1024,
+    "g" | "gb" | "gib" => 1024 * 1024 * 1024,
+    _ => return Err(format!("invalid size unit '{}'", unit)),
+  };
+  num.checked_mul(multiplier).ok_or_else(|| format!("size '{}' is too large", s))
+}
+
+/// 解析 `key=value` 形式的对象自定义元数据。key 仅允许字母、数字、`-` 与 `_`，value 须为可打印的 ASCII 字符，
+/// 以便直接作为请求头发送
+pub fn parse_meta(s: &str) -> Result<(String, String), String> {
+  let (key, value) = s.split_once('=').ok_or_else(|| format!("invalid metadata '{}', expect key=value", s))?;
+  let key = key.trim();
+  if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+    return Err(format!("invalid metadata key '{}'", key));
+  }
+  if !value.chars().all(|c| c.is_ascii_graphic() || c == ' ') {
+    return Err(format!("invalid metadata value '{}', only printable ASCII is allowed", value));
+  }
+  Ok((key.to_ascii_lowercase(), value.to_string()))
+}
+
+/// 解析带单位的时长，如 `30s`、`15m`、`1h`、`7d`，无单位时按秒计算
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+  let s = s.trim();
+  let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+  let (num, unit) = s.split_at(split);
+  let num = num.parse::<u64>().map_err(|_| format!("invalid duration '{}'", s))?;
+  let multiplier = match unit.trim().to_ascii_lowercase().as_str() {
+    "" | "s" => 1,
+    "m" => 60,
+    "h" => 60 * 60,
+    "d" => 24 * 60 * 60,
+    _ => return Err(format!("invalid duration unit '{}'", unit)),
+  };
+  num.checked_mul(multiplier).map(Duration::from_secs).ok_or_else(|| format!("duration '{}' is too large", s))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_size() {
+    assert_eq!(parse_size("8388608"), Ok(8 * 1024 * 1024));
+    assert_eq!(parse_size("512K"), Ok(512 * 1024));
+    assert_eq!(parse_size("8MiB"), Ok(8 * 1024 * 1024));
+    assert_eq!(parse_size("1 GB"), Ok(1024 * 1024 * 1024));
+    assert!(parse_size("8XB").is_err());
+    assert!(parse_size("MiB").is_err());
+  }
+
+  #[test]
+  fn test_parse_meta() {
+    assert_eq!(parse_meta("Build-Id=42"), Ok(("build-id".to_string(), "42".to_string())));
+    assert_eq!(parse_meta("commit=a1b2 c3"), Ok(("commit".to_string(), "a1b2 c3".to_string())));
+    assert_eq!(parse_meta("empty="), Ok(("empty".to_string(), String::new())));
+    assert!(parse_meta("no-value").is_err());
+    assert!(parse_meta("=42").is_err());
+    assert!(parse_meta("a b=42").is_err());
+    assert!(parse_meta("owner=张三").is_err());
+  }
+
+  #[test]
+  fn test_parse_duration() {
+    assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+    assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+    assert_eq!(parse_duration("15m"), Ok(Duration::from_secs(15 * 60)));
+    assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(60 * 60)));
+    assert_eq!(parse_duration("7d"), Ok(Duration::from_secs(7 * 24 * 60 * 60)));
+    assert!(parse_duration("1w").is_err());
+    assert!(parse_duration("h").is_err());
   }
 }
@@ -0,0 +1,414 @@
+//! 基于 `fs` 与 `memory` 存储的 FileOperation 离线测试，不需要访问云存储
+
+#![cfg(all(feature = "services-memory", feature = "services-fs"))]
+
+use std::{
+  io::IsTerminal,
+  path::{Path, PathBuf},
+  time::SystemTime,
+};
+
+use anyhow::Result;
+use config::FileFormat;
+use opendal::Operator;
+use storage_cli::{
+  client::StorageClient,
+  cmd::{ByteRange, FileOperation, SyncDirection, UploadOptions},
+  conf::DevopsConf,
+};
+
+/// 创建测试专用的临时目录，已存在时先清空
+fn test_dir(name: &str) -> Result<PathBuf> {
+  let dir = std::env::temp_dir().join(format!("storage-cli-test-{}-{}", std::process::id(), name));
+  if dir.exists() {
+    std::fs::remove_dir_all(&dir)?;
+  }
+  std::fs::create_dir_all(&dir)?;
+  Ok(dir)
+}
+
+fn memory_operator() -> Result<Operator> {
+  Ok(Operator::new(opendal::services::Memory::default())?.finish())
+}
+
+/// fs 存储的配置，断点续传日志写入 root 旁的目录，不使用用户的状态目录
+fn fs_conf(root: &str) -> Result<DevopsConf> {
+  let root = root.replace('\\', "/");
+  let toml = format!(
+    "service = \"fs\"\n\n[storage]\nroot = \"{}\"\n\n[transfer]\njournal_dir = \"{}.journal\"\n",
+    root, root
+  );
+  let cb = config::Config::builder().add_source(config::File::from_str(&toml, FileFormat::Toml));
+  DevopsConf::from_config_builder(cb)
+}
+
+fn put(src: &str, object_key: &str) -> FileOperation {
+  FileOperation::Put {
+    src: src.into(),
+    object_key: object_key.into(),
+    recursive: false,
+    jobs: 1,
+    resume: false,
+    upload: UploadOptions::default(),
+  }
+}
+
+fn sync(local: &Path, prefix: &str, direction: SyncDirection, delete: bool, dry_run: bool) -> FileOperation {
+  FileOperation::Sync {
+    local: local.to_string_lossy().to_string(),
+    prefix: prefix.into(),
+    direction,
+    delete,
+    yes: true,
+    dry_run,
+    jobs: 2,
+    upload: UploadOptions::default(),
+  }
+}
+
+fn modified(path: &Path) -> Result<SystemTime> {
+  Ok(std::fs::metadata(path)?.modified()?)
+}
+
+fn get(object_key: &str, dst: &str) -> FileOperation {
+  FileOperation::Get {
+    object_key: object_key.into(),
+    dst: dst.into(),
+    recursive: false,
+    jobs: 1,
+    overwrite: false,
+    skip_existing: false,
+    resume: false,
+    range: None,
+  }
+}
+
+#[tokio::test]
+async fn test_memory_put_get_stat_list() -> Result<()> {
+  let dir = test_dir("memory")?;
+  let src = dir.join("src.txt");
+  std::fs::write(&src, b"hello devops-cli")?;
+  let op = memory_operator()?;
+
+  put(&src.to_string_lossy(), "software/demo/src.txt").execute_with_operator(&op).await?;
+  assert_eq!(op.stat("software/demo/src.txt").await?.content_length(), 16);
+
+  FileOperation::Stat { object_key: "software/demo/src.txt".into() }.execute_with_operator(&op).await?;
+  FileOperation::List { prefix: "software/".into(), recursive: true, limit: None }.execute_with_operator(&op).await?;
+
+  let dst = dir.join("dst.txt");
+  get("software/demo/src.txt", &dst.to_string_lossy()).execute_with_operator(&op).await?;
+  assert_eq!(std::fs::read(&dst)?, b"hello devops-cli");
+
+  // 本地文件已存在时默认失败
+  assert!(get("software/demo/src.txt", &dst.to_string_lossy()).execute_with_operator(&op).await.is_err());
+
+  // 下载失败时不破坏已存在的文件，也不留下临时文件
+  let mut get_missing = get("software/demo/missing.txt", &dst.to_string_lossy());
+  if let FileOperation::Get { overwrite, .. } = &mut get_missing {
+    *overwrite = true;
+  }
+  assert!(get_missing.execute_with_operator(&op).await.is_err());
+  assert_eq!(std::fs::read(&dst)?, b"hello devops-cli");
+  assert_eq!(std::fs::read_dir(&dir)?.count(), 2);
+
+  // 标准输入不是终端时无法确认，未指定 --yes 的删除失败且不删除对象
+  if !std::io::stdin().is_terminal() {
+    let object_key = "software/demo/src.txt".into();
+    let delete = FileOperation::Delete { object_key, recursive: false, yes: false, dry_run: false };
+    assert!(delete.execute_with_operator(&op).await.is_err());
+    assert!(op.is_exist("software/demo/src.txt").await?);
+  }
+
+  FileOperation::Delete { object_key: "software/demo/src.txt".into(), recursive: false, yes: true, dry_run: false }
+    .execute_with_operator(&op)
+    .await?;
+  assert!(!op.is_exist("software/demo/src.txt").await?);
+
+  std::fs::remove_dir_all(dir)?;
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_memory_recursive_put_get() -> Result<()> {
+  let dir = test_dir("memory-recursive")?;
+  let src = dir.join("dist");
+  std::fs::create_dir_all(src.join("bin"))?;
+  std::fs::write(src.join("README.md"), b"readme")?;
+  std::fs::write(src.join("bin").join("app"), b"binary")?;
+  let op = memory_operator()?;
+
+  let src = src.to_string_lossy().to_string();
+  let upload = UploadOptions { chunk_size: 4, concurrency: 2, ..Default::default() };
+  let put_dir =
+    FileOperation::Put { src, object_key: "releases/v1.2/".into(), recursive: true, jobs: 2, resume: false, upload };
+  put_dir.execute_with_operator(&op).await?;
+  assert_eq!(op.read("releases/v1.2/bin/app").await?.to_vec(), b"binary");
+
+  let dst = dir.join("download");
+  FileOperation::Get {
+    object_key: "releases/v1.2".into(),
+    dst: dst.to_string_lossy().into(),
+    recursive: true,
+    jobs: 2,
+    overwrite: false,
+    skip_existing: true,
+    resume: false,
+    range: None,
+  }
+  .execute_with_operator(&op)
+  .await?;
+  assert_eq!(std::fs::read(dst.join("README.md"))?, b"readme");
+  assert_eq!(std::fs::read(dst.join("bin").join("app"))?, b"binary");
+
+  std::fs::remove_dir_all(dir)?;
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_memory_sync_down_path_traversal() -> Result<()> {
+  let dir = test_dir("memory-sync-traversal")?;
+  let op = memory_operator()?;
+  op.write("backup/ok.txt", "ok").await?;
+  op.write("backup/../../escaped.txt", "evil").await?;
+
+  // 越出本地目录的 key 计为失败，其余文件正常同步
+  let dst = dir.join("a").join("dst");
+  assert!(sync(&dst, "backup", SyncDirection::Down, false, false).execute_with_operator(&op).await.is_err());
+  assert_eq!(std::fs::read(dst.join("ok.txt"))?, b"ok");
+  assert!(!dir.join("escaped.txt").exists());
+
+  std::fs::remove_dir_all(dir)?;
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_memory_range_preview() -> Result<()> {
+  let dir = test_dir("memory-range")?;
+  let op = memory_operator()?;
+  op.write("logs/app.log", "line 1\nline 2\nline 3\n").await?;
+
+  let dst = dir.join("part.log");
+  let mut get_range = get("logs/app.log", &dst.to_string_lossy());
+  if let FileOperation::Get { range, .. } = &mut get_range {
+    *range = Some("7-13".parse().map_err(anyhow::Error::msg)?);
+  }
+  get_range.execute_with_operator(&op).await?;
+  assert_eq!(std::fs::read(&dst)?, b"line 2\n");
+
+  let client = StorageClient::from_operator(op.clone());
+  let output = |file_operation: FileOperation| {
+    let client = &client;
+    async move {
+      let mut out = Vec::new();
+      file_operation.execute_with_output(client, &mut out).await?;
+      anyhow::Ok(out)
+    }
+  };
+  let cat = FileOperation::Cat { object_key: "logs/app.log".into(), range: None };
+  assert_eq!(output(cat).await?, b"line 1\nline 2\nline 3\n");
+  let cat = FileOperation::Cat { object_key: "logs/app.log".into(), range: Some(ByteRange::Suffix(3)) };
+  assert_eq!(output(cat).await?, b" 3\n");
+  let head = FileOperation::Head { object_key: "logs/app.log".into(), lines: 2, bytes: None };
+  assert_eq!(output(head).await?, b"line 1\nline 2\n");
+  let head = FileOperation::Head { object_key: "logs/app.log".into(), lines: 1, bytes: Some(4) };
+  assert_eq!(output(head).await?, b"line");
+  let tail = FileOperation::Tail { object_key: "logs/app.log".into(), bytes: 7 };
+  assert_eq!(output(tail).await?, b"line 3\n");
+  assert!(FileOperation::Cat { object_key: "logs/app.log".into(), range: Some(ByteRange::From(100)) }
+    .execute_with_operator(&op)
+    .await
+    .is_err());
+
+  std::fs::remove_dir_all(dir)?;
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_memory_stdio_path() -> Result<()> {
+  let op = memory_operator()?;
+  op.write("logs/app.log", "hello stdout\n").await?;
+
+  let mut out = Vec::new();
+  get("logs/app.log", "-").execute_with_output(&StorageClient::from_operator(op.clone()), &mut out).await?;
+  assert_eq!(out, b"hello stdout\n");
+
+  let upload = UploadOptions::default();
+  let put_stdin =
+    FileOperation::Put { src: "-".into(), object_key: "logs/".into(), recursive: true, jobs: 1, resume: false, upload };
+  assert!(put_stdin.execute_with_operator(&op).await.is_err());
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_fs_put_get_copy() -> Result<()> {
+  let dir = test_dir("fs")?;
+  let root = dir.join("bucket");
+  let conf = fs_conf(&root.to_string_lossy())?;
+
+  let src = dir.join("src.txt");
+  std::fs::write(&src, b"hello fs")?;
+  put(&src.to_string_lossy(), "staging/app").execute(&conf).await?;
+  assert_eq!(std::fs::read(root.join("staging").join("app"))?, b"hello fs");
+
+  FileOperation::Copy { src_key: "staging/app".into(), dst_key: "release/app".into() }.execute(&conf).await?;
+  FileOperation::Move { src_key: "release/app".into(), dst_key: "release/app-v1".into() }.execute(&conf).await?;
+  assert!(!root.join("release").join("app").exists());
+
+  let dst = dir.join("dst.txt");
+  get("release/app-v1", &dst.to_string_lossy()).execute(&conf).await?;
+  assert_eq!(std::fs::read(&dst)?, b"hello fs");
+
+  std::fs::remove_dir_all(dir)?;
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_fs_resume() -> Result<()> {
+  let dir = test_dir("fs-resume")?;
+  let root = dir.join("bucket");
+  let conf = fs_conf(&root.to_string_lossy())?;
+  let content = (0..100_000u32).flat_map(|i| i.to_le_bytes()).collect::<Vec<_>>();
+
+  // 断点续传上传依赖 S3 兼容的分段上传接口，fs 不支持时应明确报错
+  let src = dir.join("src.bin");
+  std::fs::write(&src, &content)?;
+  let src = src.to_string_lossy().to_string();
+  let mut op = put(&src, "software/app.bin");
+  if let FileOperation::Put { resume, .. } = &mut op {
+    *resume = true;
+  }
+  let err = op.execute(&conf).await.unwrap_err();
+  assert!(err.to_string().contains("does not support resumable upload"), "{}", err);
+  put(&src, "software/app.bin").execute(&conf).await?;
+  assert_eq!(std::fs::read(root.join("software").join("app.bin"))?, content);
+
+  // 本地已有其它内容的文件且没有续传日志时不能在其后追加，也不能覆盖，需同时指定 --overwrite 重新下载
+  let dst = dir.join("dst.bin");
+  std::fs::write(&dst, vec![0u8; 4096])?;
+  let mut op = get("software/app.bin", &dst.to_string_lossy());
+  if let FileOperation::Get { resume, .. } = &mut op {
+    *resume = true;
+  }
+  let err = op.execute(&conf).await.unwrap_err();
+  assert!(err.to_string().contains("without a download journal"), "{}", err);
+  assert_eq!(std::fs::read(&dst)?, vec![0u8; 4096]);
+  if let FileOperation::Get { overwrite, .. } = &mut op {
+    *overwrite = true;
+  }
+  op.execute(&conf).await?;
+  assert_eq!(std::fs::read(&dst)?, content);
+
+  std::fs::remove_dir_all(dir)?;
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_fs_sync() -> Result<()> {
+  let dir = test_dir("fs-sync")?;
+  let root = dir.join("bucket");
+  let conf = fs_conf(&root.to_string_lossy())?;
+  let src = dir.join("src");
+  std::fs::create_dir_all(src.join("sub"))?;
+  std::fs::write(src.join("a.txt"), b"a")?;
+  std::fs::write(src.join("sub").join("b.txt"), b"bb")?;
+  let remote = root.join("backup");
+
+  sync(&src, "backup", SyncDirection::Up, false, false).execute(&conf).await?;
+  assert_eq!(std::fs::read(remote.join("a.txt"))?, b"a");
+  assert_eq!(std::fs::read(remote.join("sub").join("b.txt"))?, b"bb");
+
+  // 未变更时再次同步不传输任何文件，对象的修改时间不变
+  let before = (modified(&remote.join("a.txt"))?, modified(&remote.join("sub").join("b.txt"))?);
+  sync(&src, "backup", SyncDirection::Up, false, false).execute(&conf).await?;
+  assert_eq!((modified(&remote.join("a.txt"))?, modified(&remote.join("sub").join("b.txt"))?), before);
+
+  // --dry-run 不修改源端与目标端
+  std::fs::remove_file(src.join("a.txt"))?;
+  std::fs::write(src.join("c.txt"), b"ccc")?;
+  sync(&src, "backup", SyncDirection::Up, true, true).execute(&conf).await?;
+  assert!(remote.join("a.txt").exists());
+  assert!(!remote.join("c.txt").exists());
+  assert!(src.join("c.txt").exists());
+
+  // --delete 删除目标端多余的对象
+  sync(&src, "backup", SyncDirection::Up, true, false).execute(&conf).await?;
+  assert!(!remote.join("a.txt").exists());
+  assert_eq!(std::fs::read(remote.join("c.txt"))?, b"ccc");
+
+  // 反向同步到本地目录，--delete 删除本地多余的文件
+  let dst = dir.join("dst");
+  std::fs::create_dir_all(&dst)?;
+  std::fs::write(dst.join("extra.txt"), b"extra")?;
+  sync(&dst, "backup", SyncDirection::Down, true, true).execute(&conf).await?;
+  assert!(dst.join("extra.txt").exists());
+  assert!(!dst.join("c.txt").exists());
+  sync(&dst, "backup", SyncDirection::Down, true, false).execute(&conf).await?;
+  assert!(!dst.join("extra.txt").exists());
+  assert_eq!(std::fs::read(dst.join("c.txt"))?, b"ccc");
+  assert_eq!(std::fs::read(dst.join("sub").join("b.txt"))?, b"bb");
+
+  let before = modified(&dst.join("c.txt"))?;
+  sync(&dst, "backup", SyncDirection::Down, false, false).execute(&conf).await?;
+  assert_eq!(modified(&dst.join("c.txt"))?, before);
+
+  // 源目录不存在或同步到 bucket 根目录时拒绝 --delete
+  let missing = dir.join("missing");
+  assert!(sync(&missing, "backup", SyncDirection::Up, true, false).execute(&conf).await.is_err());
+  assert!(sync(&src, "/", SyncDirection::Up, true, false).execute(&conf).await.is_err());
+  assert_eq!(std::fs::read(remote.join("c.txt"))?, b"ccc");
+
+  // 标准输入不是终端时无法确认，未指定 --yes 的 --delete 失败且不删除文件
+  if !std::io::stdin().is_terminal() {
+    std::fs::write(dst.join("extra.txt"), b"extra")?;
+    let mut op = sync(&dst, "backup", SyncDirection::Down, true, false);
+    if let FileOperation::Sync { yes, .. } = &mut op {
+      *yes = false;
+    }
+    assert!(op.execute(&conf).await.is_err());
+    assert!(dst.join("extra.txt").exists());
+  }
+
+  std::fs::remove_dir_all(dir)?;
+  Ok(())
+}
+
+/// 以 fs 存储运行 devops-cli，返回标准输出
+fn run_cli(conf: &Path, args: &[&str]) -> Result<String> {
+  let output = std::process::Command::new(env!("CARGO_BIN_EXE_devops-cli"))
+    .env_remove("SERVICE")
+    .arg("-f")
+    .arg(conf)
+    .args(args)
+    .stdin(std::process::Stdio::null())
+    .output()?;
+  anyhow::ensure!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+  Ok(String::from_utf8(output.stdout)?)
+}
+
+#[test]
+fn test_cli_transfer_json_output() -> Result<()> {
+  let dir = test_dir("cli-json")?;
+  let conf = dir.join("app.toml");
+  let root = dir.join("store").to_string_lossy().replace('\\', "/");
+  std::fs::write(&conf, format!("service = \"fs\"\n\n[storage]\nroot = \"{}\"\n", root))?;
+  let src = dir.join("src.txt");
+  std::fs::write(&src, b"hello devops-cli")?;
+  let dst = dir.join("dst.txt");
+
+  // -o json 时单文件的上传、下载输出传输结果
+  let put = run_cli(&conf, &["-o", "json", "put", &src.to_string_lossy(), "demo/src.txt"])?;
+  let get = run_cli(&conf, &["-o", "json", "get", "demo/src.txt", &dst.to_string_lossy()])?;
+  for output in [put, get] {
+    let result: serde_json::Value = serde_json::from_str(&output)?;
+    assert_eq!(result["object_key"], "demo/src.txt");
+    assert_eq!(result["bytes"], 16);
+    assert_eq!(result["md5"], "49fbbac83d45b203feefbfc6379b4ad7");
+  }
+
+  // table 格式时不输出传输结果
+  assert!(run_cli(&conf, &["put", &src.to_string_lossy(), "demo/src.txt"])?.is_empty());
+
+  std::fs::remove_dir_all(dir)?;
+  Ok(())
+}
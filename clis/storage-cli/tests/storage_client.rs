@@ -0,0 +1,124 @@
+//! 基于 `fs` 与 `memory` 存储的 StorageClient 离线测试，不需要访问云存储
+
+#![cfg(all(feature = "services-memory", feature = "services-fs"))]
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use config::FileFormat;
+use futures::TryStreamExt;
+use storage_cli::{
+  client::StorageClient,
+  cmd::{ByteRange, ExistingPolicy, StorageSource},
+  conf::DevopsConf,
+  error::StorageError,
+};
+
+/// 创建测试专用的临时目录，已存在时先清空
+fn test_dir(name: &str) -> Result<PathBuf> {
+  let dir = std::env::temp_dir().join(format!("storage-cli-client-test-{}-{}", std::process::id(), name));
+  if dir.exists() {
+    std::fs::remove_dir_all(&dir)?;
+  }
+  std::fs::create_dir_all(&dir)?;
+  Ok(dir)
+}
+
+#[tokio::test]
+async fn test_memory_client() -> Result<()> {
+  let dir = test_dir("memory")?;
+  let src = dir.join("src.txt");
+  std::fs::write(&src, b"123456789")?;
+  let client = StorageClient::builder(StorageSource::Memory).build()?;
+
+  let uploaded = client.upload_file(&src, "demo/src.txt").await?;
+  assert_eq!(uploaded.bytes, 9);
+  assert_eq!(uploaded.md5, "25f9e794323b453885f5181f1b624d0b");
+
+  let meta = client.stat("demo/src.txt").await?;
+  assert_eq!(meta.mode, "file");
+  assert_eq!(meta.content_length, 9);
+
+  let entries = client.list("demo/", false, None).await?;
+  assert_eq!(entries.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(), ["demo/src.txt"]);
+
+  let dst = dir.join("dst.txt");
+  let downloaded = client.download_file("demo/src.txt", &dst).await?;
+  assert_eq!(downloaded, uploaded);
+  assert_eq!(std::fs::read(&dst)?, b"123456789");
+
+  let err = client.download_file_with("demo/src.txt", &dst, ExistingPolicy::Fail, None).await.unwrap_err();
+  assert!(matches!(err, StorageError::AlreadyExists(path) if path == dst.display().to_string()));
+  assert!(client.download_file_with("demo/src.txt", &dst, ExistingPolicy::Skip, None).await?.is_none());
+
+  let part = dir.join("part.txt");
+  let range = Some(ByteRange::Suffix(3));
+  let downloaded = client.download_file_with("demo/src.txt", &part, ExistingPolicy::Fail, range).await?;
+  assert_eq!(downloaded.map(|r| r.bytes), Some(3));
+  assert_eq!(std::fs::read(&part)?, b"789");
+
+  let err = client.stat("demo/missing.txt").await.unwrap_err();
+  assert!(matches!(err, StorageError::NotFound(key) if key == "demo/missing.txt"));
+  let err = client.upload_file(dir.join("missing.txt"), "demo/missing.txt").await.unwrap_err();
+  assert!(matches!(err, StorageError::NotFound(_)));
+
+  std::fs::remove_dir_all(&dir)?;
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_memory_client_dir_and_delete() -> Result<()> {
+  let dir = test_dir("memory-dir")?;
+  let src = dir.join("src");
+  std::fs::create_dir_all(src.join("sub"))?;
+  std::fs::write(src.join("a.txt"), b"a")?;
+  std::fs::write(src.join("sub").join("b.txt"), b"bb")?;
+  let client = StorageClient::builder(StorageSource::Memory).build()?;
+
+  let uploaded = client.upload_dir(&src, "demo", 2, &Default::default()).await?;
+  let mut keys = uploaded.transferred.iter().map(|r| r.object_key.as_str()).collect::<Vec<_>>();
+  keys.sort();
+  assert_eq!(keys, ["demo/a.txt", "demo/sub/b.txt"]);
+  assert!(uploaded.failed.is_empty());
+
+  let entries = client.list_stream("demo/", true, Some(1)).await?.try_collect::<Vec<_>>().await?;
+  assert_eq!(entries.len(), 1);
+
+  let dst = dir.join("dst");
+  let downloaded = client.download_dir("demo/", &dst, 2, ExistingPolicy::Fail).await?;
+  assert_eq!(downloaded.transferred.iter().map(|r| r.bytes).sum::<u64>(), 3);
+  assert_eq!(std::fs::read(dst.join("sub").join("b.txt"))?, b"bb");
+  let downloaded = client.download_dir("demo/", &dst, 2, ExistingPolicy::Skip).await?;
+  assert_eq!(downloaded.skipped.len(), 2);
+  let downloaded = client.download_dir("demo/", &dst, 2, ExistingPolicy::Fail).await?;
+  assert!(downloaded.failed.iter().all(|(_, e)| matches!(e, StorageError::AlreadyExists(_))));
+  assert_eq!(downloaded.failed.len(), 2);
+
+  client.delete("demo/a.txt").await?;
+  client.delete_all(vec!["demo/sub/b.txt".to_string()]).await?;
+  assert!(client.list("demo/", true, None).await?.iter().all(|e| e.mode == "dir"));
+
+  std::fs::remove_dir_all(&dir)?;
+  Ok(())
+}
+
+#[tokio::test]
+async fn test_fs_client_from_conf() -> Result<()> {
+  let dir = test_dir("fs")?;
+  let root = dir.join("bucket");
+  let toml = format!("service = \"fs\"\n\n[storage]\nroot = \"{}\"\n", root.to_string_lossy().replace('\\', "/"));
+  let cb = config::Config::builder().add_source(config::File::from_str(&toml, FileFormat::Toml));
+  let client = StorageClient::new(&DevopsConf::from_config_builder(cb)?).await?;
+
+  let src = dir.join("src.txt");
+  std::fs::write(&src, b"hello")?;
+  client.upload_file(&src, "a/b.txt").await?;
+  assert_eq!(std::fs::read(root.join("a").join("b.txt"))?, b"hello");
+
+  let entries = client.list("a/", true, None).await?;
+  assert_eq!(entries.len(), 1);
+  assert_eq!(entries[0].content_length, 5);
+
+  std::fs::remove_dir_all(&dir)?;
+  Ok(())
+}